@@ -10,7 +10,7 @@ import { FileExplorer } from './components/FileExplorer';
 import { AccountModal } from './components/AccountModal';
 import { FileEditorModal } from './components/FileEditorModal';
 import { ThemeProvider } from './components/ThemeProvider';
-import { s3Service } from './services/s3Service';
+import { connectionFor, s3Service } from './services/s3Service';
 import { versionService } from './services/versionService';
 import { UploadConflictModal } from './components/UploadConflictModal';
 import { activityService } from './services/activityService';
@@ -363,6 +363,7 @@ const App: React.FC = () => {
         region: activeAcc.region,
         accessKeyId: activeAcc.accessKeyId,
         secretAccessKey: activeAcc.secretAccessKey,
+        connection: connectionFor(activeAcc),
         bucket: activeAcc.bucketName,
         prefix: prefix,
         paths: paths,
@@ -372,7 +373,7 @@ const App: React.FC = () => {
       refreshObjects();
     } catch (err: any) {
       console.error('Native upload failed:', err);
-      showToast(`Upload failed: ${err}`, 'error');
+      showToast(`Upload failed: ${err?.message ?? err}`, 'error');
     } finally {
       finishOperation(true);
       setTimeout(() => setUploadStatus(null), 3000);
@@ -1321,7 +1322,9 @@ const App: React.FC = () => {
         format,
         accessKeyId: activeAccount.accessKeyId,
         secretAccessKey: activeAccount.secretAccessKey,
+        connection: connectionFor(activeAccount),
         region: activeAccount.region,
+        endpoint: (activeAccount.endpoint && activeAccount.endpoint.includes('amazonaws.com')) ? '' : activeAccount.endpoint,
       });
 
       if (isDownload) {
@@ -1341,6 +1344,7 @@ const App: React.FC = () => {
           region: activeAccount.region,
           accessKeyId: activeAccount.accessKeyId,
           secretAccessKey: activeAccount.secretAccessKey,
+          connection: connectionFor(activeAccount),
           bucket: activeAccount.bucketName,
           key: `${currentPrefix}${fileName}`,
           path: savePath
@@ -1744,6 +1748,7 @@ const App: React.FC = () => {
           region: activeAccount.region,
           accessKeyId: activeAccount.accessKeyId,
           secretAccessKey: activeAccount.secretAccessKey,
+          connection: connectionFor(activeAccount),
           bucket: activeAccount.bucketName,
           key: file.key,
           path: savePath
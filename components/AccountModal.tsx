@@ -163,7 +163,8 @@ export const AccountModal: React.FC<AccountModalProps> = ({ isOpen, onClose, onS
           bucketName: formData.bucketName!,
           accessMode: result.accessMode,
           enableTrash,
-          enableActivityLog
+          enableActivityLog,
+          connection: formData.connection
         });
       } catch (e: any) {
         setTestStatus('error');
@@ -183,7 +184,8 @@ export const AccountModal: React.FC<AccountModalProps> = ({ isOpen, onClose, onS
       bucketName: formData.bucketName!,
       accessMode: accessMode || 'read-only',
       enableTrash,
-      enableActivityLog
+      enableActivityLog,
+      connection: formData.connection
     });
   };
 
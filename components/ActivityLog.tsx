@@ -382,8 +382,8 @@ export const ActivityLog: React.FC<ActivityLogProps> = ({ activeAccount, onNavig
                                                     <XCircle size={14} />
                                                     <span className="font-medium">Failed</span>
                                                     {entry.error_message && (
-                                                        <span className="text-xs text-[var(--text-tertiary)] ml-1 truncate max-w-xs" title={entry.error_message}>
-                                                            ({entry.error_message})
+                                                        <span className="text-xs text-[var(--text-tertiary)] ml-1 truncate max-w-xs" title={[entry.error_message.code, entry.error_message.request_id && `Request ID: ${entry.error_message.request_id}`].filter(Boolean).join(' · ') || entry.error_message.message}>
+                                                            ({entry.error_message.message})
                                                         </span>
                                                     )}
                                                 </div>
@@ -5,6 +5,8 @@ import { invoke } from '@tauri-apps/api/core';
 
 interface BucketInfo {
   name: string;
+  region?: string;
+  creationDate?: string;
   accessLevel: 'read-only' | 'read-write';
   isSelected: boolean;
   alreadyAdded: boolean;
@@ -76,18 +78,20 @@ export const MultiBucketModal: React.FC<MultiBucketModalProps> = ({
 
     try {
       // Call Rust backend to list buckets
-      const bucketList: string[] = await invoke('list_buckets', {
+      const bucketList: { name: string; region?: string; creation_date?: string }[] = await invoke('list_buckets', {
         endpoint,
         region,
         accessKeyId: accessKey.trim(),
         secretAccessKey: secretKey.trim(),
       });
 
-      const bucketInfos: BucketInfo[] = bucketList.map(name => ({
-        name: name,
-        accessLevel: 'read-write', // Default to read-write as list_buckets only returns names
+      const bucketInfos: BucketInfo[] = bucketList.map(b => ({
+        name: b.name,
+        region: b.region ?? undefined,
+        creationDate: b.creation_date ?? undefined,
+        accessLevel: 'read-write', // Default to read-write as list_buckets doesn't report permissions
         isSelected: false,
-        alreadyAdded: existingBuckets.includes(name),
+        alreadyAdded: existingBuckets.includes(b.name),
       }));
 
       setBuckets(bucketInfos);
@@ -135,7 +139,7 @@ export const MultiBucketModal: React.FC<MultiBucketModalProps> = ({
         // "endpoint: provider === 'aws' ? '' : endpoint" logic from previous version seems to rely on App.tsx or service to default AWS.
         // But in this file we set a default AWS endpoint. Let's pass it if it's not the generic one, or just empty for AWS to be safe.
         // Reverting to previous logic for safety:
-        region,
+        region: b.region || region,
         bucketName: b.name,
         accessMode: b.accessLevel,
         accessKeyId: accessKey,
@@ -389,6 +393,9 @@ export const MultiBucketModal: React.FC<MultiBucketModalProps> = ({
                           <span className="text-[10px] text-[var(--text-tertiary)]">
                             {bucket.accessLevel === 'read-only' ? 'Read-only Access' : 'Read & Write Access'}
                           </span>
+                          {bucket.region && (
+                            <span className="text-[10px] text-[var(--text-tertiary)]">· {bucket.region}</span>
+                          )}
                         </div>
                       </div>
                     </div>
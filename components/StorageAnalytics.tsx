@@ -3,8 +3,8 @@ import {
     BarChart, Bar, PieChart, Pie, Cell, XAxis, YAxis,
     CartesianGrid, Tooltip, Legend, ResponsiveContainer
 } from 'recharts';
-import { ArrowLeft, RefreshCw, AlertCircle, HardDrive, FileText, Database, Clock, ChevronRight } from 'lucide-react';
-import { S3Account, BucketAnalytics, S3Object } from '../types';
+import { ArrowLeft, RefreshCw, AlertCircle, HardDrive, FileText, Database, Clock, ChevronRight, Download } from 'lucide-react';
+import { S3Account, BucketAnalytics, S3Object, AnalyticsTrendPoint } from '../types';
 import { s3Service } from '../services/s3Service';
 
 interface StorageAnalyticsProps {
@@ -16,6 +16,7 @@ const COLORS = ['#3b82f6', '#8b5cf6', '#ec4899', '#f59e0b', '#10b981', '#64748b'
 
 export const StorageAnalytics: React.FC<StorageAnalyticsProps> = ({ activeAccount, onNavigateBack }) => {
     const [analytics, setAnalytics] = useState<BucketAnalytics | null>(null);
+    const [history, setHistory] = useState<AnalyticsTrendPoint[]>([]);
     const [isLoading, setIsLoading] = useState(false);
     const [scanProgress, setScanProgress] = useState(0);
     const [error, setError] = useState<string | null>(null);
@@ -32,6 +33,7 @@ export const StorageAnalytics: React.FC<StorageAnalyticsProps> = ({ activeAccoun
                 setScanProgress(scanned);
             });
             setAnalytics(data);
+            setHistory(await s3Service.getAnalyticsHistory(activeAccount, activeAccount.bucketName));
         } catch (err: any) {
             console.error('Failed to load analytics:', err);
             setError(err.message || 'Failed to scan bucket. Please check your permissions.');
@@ -40,10 +42,52 @@ export const StorageAnalytics: React.FC<StorageAnalyticsProps> = ({ activeAccoun
         }
     };
 
+    // Show the last stored analysis; only scan buckets never analyzed before
+    const loadStoredAnalytics = async () => {
+        if (!activeAccount || !activeAccount.bucketName) return;
+
+        try {
+            const stored = await s3Service.getStoredAnalytics(activeAccount, activeAccount.bucketName);
+            if (!stored) {
+                await loadAnalytics();
+                return;
+            }
+            setAnalytics(stored);
+            setHistory(await s3Service.getAnalyticsHistory(activeAccount, activeAccount.bucketName));
+        } catch (err) {
+            console.error('Failed to load stored analytics:', err);
+            await loadAnalytics();
+        }
+    };
+
     useEffect(() => {
-        loadAnalytics();
+        setAnalytics(null);
+        setHistory([]);
+        loadStoredAnalytics();
     }, [activeAccount]);
 
+    const handleExport = async (format: 'json' | 'csv') => {
+        if (!activeAccount || !activeAccount.bucketName || !analytics) return;
+
+        try {
+            const data = await s3Service.exportAnalytics(activeAccount, activeAccount.bucketName, format, analytics.id);
+            const blob = new Blob([data], {
+                type: format === 'json' ? 'application/json' : 'text/csv'
+            });
+            const url = URL.createObjectURL(blob);
+            const a = document.createElement('a');
+            a.href = url;
+            a.download = `${activeAccount.bucketName}-analytics-${new Date(analytics.lastUpdated).toISOString().split('T')[0]}.${format}`;
+            document.body.appendChild(a);
+            a.click();
+            document.body.removeChild(a);
+            URL.revokeObjectURL(url);
+        } catch (err: any) {
+            console.error('Failed to export analytics:', err);
+            setError(err.message || 'Failed to export analytics.');
+        }
+    };
+
     const formatSize = (bytes: number) => {
         if (bytes === 0) return '0 B';
         const k = 1024;
@@ -92,6 +136,16 @@ export const StorageAnalytics: React.FC<StorageAnalyticsProps> = ({ activeAccoun
                 </div>
 
                 <div className="flex items-center gap-3">
+                    {analytics?.id !== undefined && !isLoading && (['csv', 'json'] as const).map(format => (
+                        <button
+                            key={format}
+                            onClick={() => handleExport(format)}
+                            className="flex items-center gap-2 px-3 py-1.5 bg-[var(--bg-tertiary)] hover:bg-[var(--bg-quaternary)] text-[var(--text-secondary)] rounded-lg text-sm font-medium transition-all"
+                        >
+                            <Download size={14} />
+                            {format.toUpperCase()}
+                        </button>
+                    ))}
                     <button
                         onClick={loadAnalytics}
                         disabled={isLoading}
@@ -138,6 +192,11 @@ export const StorageAnalytics: React.FC<StorageAnalyticsProps> = ({ activeAccoun
                             </div>
                             <div className="text-2xl font-bold text-[var(--text-primary)]">{formatSize(analytics.totalSize)}</div>
                             <div className="text-xs text-[var(--text-tertiary)] mt-2">Distributed across {analytics.totalObjects.toLocaleString()} objects</div>
+                            {analytics.incompleteUploads && analytics.incompleteUploads.size > 0 && (
+                                <div className="text-xs text-amber-500 mt-1">
+                                    +{formatSize(analytics.incompleteUploads.size)} in {analytics.incompleteUploads.uploads.toLocaleString()} incomplete uploads
+                                </div>
+                            )}
                         </div>
 
                         {/* Summary: Top Categories */}
@@ -232,6 +291,33 @@ export const StorageAnalytics: React.FC<StorageAnalyticsProps> = ({ activeAccoun
                             </div>
                         </div>
 
+                        {/* Size Over Time */}
+                        {history.length > 1 && (
+                            <div className="lg:col-span-4 bg-[var(--bg-secondary)] p-5 rounded-2xl border border-[var(--border-primary)] flex flex-col">
+                                <span className="text-xs font-semibold text-[var(--text-tertiary)] uppercase tracking-wider mb-4">Size Over Time</span>
+                                <div className="h-48">
+                                    <ResponsiveContainer width="100%" height="100%">
+                                        <BarChart data={history}>
+                                            <CartesianGrid strokeDasharray="3 3" vertical={false} stroke="var(--border-primary)" />
+                                            <XAxis
+                                                dataKey="analyzedAt"
+                                                style={{ fontSize: '10px' }}
+                                                tickFormatter={(value: number) => new Date(value).toLocaleDateString()}
+                                            />
+                                            <YAxis style={{ fontSize: '10px' }} width={70} tickFormatter={(value: number) => formatSize(value)} />
+                                            <Tooltip
+                                                cursor={{ fill: 'transparent' }}
+                                                contentStyle={{ backgroundColor: 'var(--bg-secondary)', border: '1px solid var(--border-primary)', borderRadius: '8px' }}
+                                                labelFormatter={(value: any) => new Date(value).toLocaleString()}
+                                                formatter={(value: any) => [formatSize(value), 'Size']}
+                                            />
+                                            <Bar dataKey="totalSize" fill={COLORS[0]} radius={[4, 4, 0, 0]} />
+                                        </BarChart>
+                                    </ResponsiveContainer>
+                                </div>
+                            </div>
+                        )}
+
                         {/* Largest Files Table */}
                         <div className="lg:col-span-4 bg-[var(--bg-secondary)] rounded-2xl border border-[var(--border-primary)] overflow-hidden">
                             <div className="px-6 py-4 border-b border-[var(--border-primary)] flex items-center justify-between">
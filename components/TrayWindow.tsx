@@ -1,7 +1,7 @@
 import React, { useState, useEffect } from 'react';
 import { Upload, Database, Maximize, Power, RefreshCw, FileText, Folder, CheckCircle2, AlertCircle, Cloud, ChevronRight, CornerDownRight, ArrowUpRight, ArrowDownLeft, Clock, Server, Eye, Plus, Link2 } from 'lucide-react';
 import { writeText } from '@tauri-apps/plugin-clipboard-manager';
-import { s3Service } from '../services/s3Service';
+import { connectionFor, s3Service } from '../services/s3Service';
 import { S3Account, S3Object, SyncJob } from '../types';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { getCurrentWebview } from '@tauri-apps/api/webview';
@@ -226,6 +226,7 @@ export const TrayWindow: React.FC = () => {
                 region: activeAcc.region,
                 accessKeyId: activeAcc.accessKeyId,
                 secretAccessKey: activeAcc.secretAccessKey,
+                connection: connectionFor(activeAcc),
                 bucket: activeAcc.bucketName,
                 prefix: '',
                 paths: paths,
@@ -338,6 +339,7 @@ export const TrayWindow: React.FC = () => {
                 region: activeAccount.region,
                 accessKeyId: activeAccount.accessKeyId,
                 secretAccessKey: activeAccount.secretAccessKey,
+                connection: connectionFor(activeAccount),
                 bucket: activeAccount.bucketName || '',
                 key: obj.key,
                 expiresIn: 3600 // 1 hour
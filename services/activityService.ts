@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
-import { ActivityLogEntry, ActivityLogFilters, S3Account } from '../types';
+import { ActivityLogEntry, ActivityLogFilters, BucketStackError, S3Account } from '../types';
 
 export const activityService = {
     /**
@@ -12,7 +12,7 @@ export const activityService = {
         objectPathBefore?: string,
         objectPathAfter?: string,
         status: 'success' | 'failed' = 'success',
-        errorMessage?: string,
+        errorMessage?: string | BucketStackError,
         fileSize?: number
     ): Promise<void> => {
         // Only log if activity logging is enabled for this account
@@ -1,4 +1,4 @@
-import { BucketAnalytics, FavouriteItem, S3Account, S3AccountMetadata, S3Bucket, S3Object, SyncStats, TransferJob, TransferProgress } from "../types";
+import { AnalyticsTrendPoint, BucketAnalytics, ConnectionOptions, FavouriteItem, S3Account, S3AccountMetadata, S3Bucket, S3Object, SyncStats, TransferJob, TransferProgress } from "../types";
 import JSZip from 'jszip';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
@@ -24,6 +24,71 @@ const safeInvoke = async <T = any>(cmd: string, args?: Record<string, any>): Pro
 
 const ACCOUNTS_KEY = 's3_desktop_accounts';
 
+// Secret connection fields live in secure storage as bucketstack-{id}-{suffix};
+// the rest of the options is saved with the account metadata
+const CONNECTION_SECRET_SUFFIXES = ['session-token', 'web-identity-token', 'proxy-password', 'client-key'];
+
+const splitConnectionSecrets = (connection?: ConnectionOptions): { stored?: ConnectionOptions; secrets: Record<string, string> } => {
+  if (!connection) return { stored: undefined, secrets: {} };
+  // connection_id is always the account id; MFA codes are single-use
+  const { session_token, connection_id, ...stored } = connection;
+  const secrets: Record<string, string> = {};
+  if (session_token) secrets['session-token'] = session_token;
+  if (stored.assume_role) {
+    stored.assume_role = { ...stored.assume_role, mfa_token: undefined };
+  }
+  if (stored.web_identity?.token) {
+    secrets['web-identity-token'] = stored.web_identity.token;
+    stored.web_identity = { ...stored.web_identity, token: undefined };
+  }
+  if (stored.proxy?.password) {
+    secrets['proxy-password'] = stored.proxy.password;
+    stored.proxy = { ...stored.proxy, password: undefined };
+  }
+  if (stored.tls?.client_key_pem) {
+    secrets['client-key'] = stored.tls.client_key_pem;
+    stored.tls = { ...stored.tls, client_key_pem: undefined };
+  }
+  return { stored, secrets };
+};
+
+const loadConnection = async (id: string, stored?: ConnectionOptions): Promise<ConnectionOptions | undefined> => {
+  if (!stored) return undefined;
+  const secret = (suffix: string) => secureStorage.getItem(`bucketstack-${id}-${suffix}`);
+  const connection: ConnectionOptions = { ...stored };
+  const sessionToken = await secret('session-token');
+  if (sessionToken) connection.session_token = sessionToken;
+  if (connection.web_identity) {
+    const token = await secret('web-identity-token');
+    if (token) connection.web_identity = { ...connection.web_identity, token };
+  }
+  if (connection.proxy) {
+    const password = await secret('proxy-password');
+    if (password) connection.proxy = { ...connection.proxy, password };
+  }
+  if (connection.tls) {
+    const clientKey = await secret('client-key');
+    if (clientKey) connection.tls = { ...connection.tls, client_key_pem: clientKey };
+  }
+  return connection;
+};
+
+const removeConnectionSecrets = async (id: string): Promise<void> => {
+  for (const suffix of CONNECTION_SECRET_SUFFIXES) {
+    await secureStorage.removeItem(`bucketstack-${id}-${suffix}`);
+  }
+};
+
+// Profile, default-chain and web identity connections resolve keys themselves
+const usesStoredKeys = (connection?: ConnectionOptions): boolean =>
+  !(connection?.profile || connection?.use_default_chain || connection?.web_identity);
+
+// Options sent with every backend S3 command
+export const connectionFor = (account: Partial<S3Account>): ConnectionOptions => ({
+  ...account.connection,
+  connection_id: account.id,
+});
+
 // Comprehensive application reset
 export const clearAllData = async (): Promise<void> => {
   console.warn('🗑️ Starting comprehensive application reset...');
@@ -35,6 +100,7 @@ export const clearAllData = async (): Promise<void> => {
       try {
         await secureStorage.removeItem(`bucketstack-${account.id}-access`);
         await secureStorage.removeItem(`bucketstack-${account.id}-secret`);
+        await removeConnectionSecrets(account.id);
       } catch (e) {
         console.warn(`Failed to remove secure item for account ${account.id}:`, e);
       }
@@ -85,6 +151,35 @@ const getObjectName = (key: string, prefix: string): string => {
   return relativeKey.slice(0, firstSlash);
 };
 
+// Map an analyze_bucket report to the shape the analytics view charts
+const toBucketAnalytics = (analysis: any): BucketAnalytics => {
+  const typeMap = new Map<string, { count: number, size: number }>();
+  for (const [extension, tally] of Object.entries<any>(analysis.extensions)) {
+    const type = getFileType(`file.${extension}`);
+    const current = typeMap.get(type) || { count: 0, size: 0 };
+    typeMap.set(type, { count: current.count + tally.count, size: current.size + tally.size });
+  }
+
+  return {
+    id: analysis.id ?? undefined,
+    totalSize: analysis.total.size,
+    totalObjects: analysis.total.count,
+    largestFiles: analysis.largest.slice(0, 20).map((obj: any) => ({
+      key: obj.key,
+      name: obj.key.split('/').pop() || obj.key,
+      size: obj.size,
+      lastModified: obj.last_modified ? new Date(obj.last_modified).getTime() : 0,
+      type: getFileType(obj.key),
+      isFolder: false
+    })),
+    fileTypeDistribution: Array.from(typeMap.entries()).map(([type, data]) => ({ type, ...data })),
+    storageClassDistribution: Object.entries<any>(analysis.storage_classes).map(([storageClass, data]) => ({ storageClass, count: data.count, size: data.size })),
+    ageDistribution: analysis.age.map((bucket: any) => ({ ageRange: bucket.label, count: bucket.count, size: bucket.size })),
+    lastUpdated: new Date(analysis.analyzed_at).getTime(),
+    incompleteUploads: analysis.incomplete_uploads,
+  };
+};
+
 // Migration utility for existing credentials
 const migrateToSecureStorage = async (): Promise<void> => {
   const MIGRATION_KEY = 'credentials_migrated_v3_secure_storage';
@@ -222,9 +317,10 @@ export const s3Service = {
           console.error(`Secure storage error for account ${metadata.id}:`, storageError);
         }
 
-        if (accessKeyId && secretAccessKey) {
+        if ((accessKeyId && secretAccessKey) || !usesStoredKeys(metadata.connection)) {
           accounts.push({
             ...metadata,
+            connection: await loadConnection(metadata.id, metadata.connection),
             accessKeyId: accessKeyId.trim(),
             secretAccessKey: secretAccessKey.trim()
           });
@@ -264,7 +360,7 @@ export const s3Service = {
     }
 
     // Validate account has required fields
-    if (!account.accessKeyId || !account.secretAccessKey) {
+    if (usesStoredKeys(account.connection) && (!account.accessKeyId || !account.secretAccessKey)) {
       throw new Error('Account is missing access key or secret key');
     }
 
@@ -280,9 +376,19 @@ export const s3Service = {
 
     try {
       // Store credentials securely using SecureStorage
-      await secureStorage.saveItem(`bucketstack-${account.id}-access`, account.accessKeyId.trim());
-      await secureStorage.saveItem(`bucketstack-${account.id}-secret`, account.secretAccessKey.trim());
+      if (account.accessKeyId && account.secretAccessKey) {
+        await secureStorage.saveItem(`bucketstack-${account.id}-access`, account.accessKeyId.trim());
+        await secureStorage.saveItem(`bucketstack-${account.id}-secret`, account.secretAccessKey.trim());
+      }
 
+      const { stored: connection, secrets } = splitConnectionSecrets(account.connection);
+      for (const suffix of CONNECTION_SECRET_SUFFIXES) {
+        if (secrets[suffix]) {
+          await secureStorage.saveItem(`bucketstack-${account.id}-${suffix}`, secrets[suffix]);
+        } else {
+          await secureStorage.removeItem(`bucketstack-${account.id}-${suffix}`);
+        }
+      }
 
       // Store metadata in localStorage (without credentials)
       const metadataList = await s3Service.getAccountMetadata();
@@ -295,7 +401,8 @@ export const s3Service = {
         bucketName: account.bucketName,
         accessMode: account.accessMode || 'read-only', // Default to read-only if not verified
         enableTrash: account.enableTrash,
-        enableActivityLog: account.enableActivityLog
+        enableActivityLog: account.enableActivityLog,
+        connection
       };
 
       // Filter out existing entry with same ID to prevent duplicates
@@ -326,6 +433,7 @@ export const s3Service = {
       // Delete credentials from SecureStorage
       await secureStorage.removeItem(`bucketstack-${id}-access`);
       await secureStorage.removeItem(`bucketstack-${id}-secret`);
+      await removeConnectionSecrets(id);
 
       // Remove metadata from localStorage
       const metadataList = await s3Service.getAccountMetadata();
@@ -348,6 +456,7 @@ export const s3Service = {
         try {
           await secureStorage.removeItem(`bucketstack-${account.id}-access`);
           await secureStorage.removeItem(`bucketstack-${account.id}-secret`);
+          await removeConnectionSecrets(account.id);
         } catch (e) {
           console.warn('Failed to remove item from secure storage during cleanup', e);
         }
@@ -362,7 +471,8 @@ export const s3Service = {
   },
 
   testConnection: async (account: Partial<S3Account>): Promise<{ success: boolean; accessMode: 'read-only' | 'read-write'; message: string }> => {
-    if (!account.endpoint || !account.accessKeyId || !account.secretAccessKey || !account.region || !account.bucketName) {
+    const hasKeys = !!(account.accessKeyId && account.secretAccessKey) || !usesStoredKeys(account.connection);
+    if (!account.endpoint || !hasKeys || !account.region || !account.bucketName) {
       throw new Error("Missing required credentials or bucket name");
     }
 
@@ -383,8 +493,9 @@ export const s3Service = {
       const response = await invoke<{ success: boolean; message: string }>('test_s3_connection', {
         endpoint: sanitizedEndpoint,
         region: account.region,
-        accessKeyId: account.accessKeyId.trim(),
-        secretAccessKey: account.secretAccessKey.trim(),
+        accessKeyId: (account.accessKeyId ?? '').trim(),
+        secretAccessKey: (account.secretAccessKey ?? '').trim(),
+        connection: connectionFor(account),
         testBucket: account.bucketName,
       });
 
@@ -402,8 +513,9 @@ export const s3Service = {
         await invoke<boolean>('upload_file', {
           endpoint: sanitizedEndpoint,
           region: account.region,
-          accessKeyId: account.accessKeyId.trim(),
-          secretAccessKey: account.secretAccessKey.trim(),
+          accessKeyId: (account.accessKeyId ?? '').trim(),
+          secretAccessKey: (account.secretAccessKey ?? '').trim(),
+          connection: connectionFor(account),
           bucket: account.bucketName,
           key: probeKey,
           body: [32], // Single space byte
@@ -416,8 +528,9 @@ export const s3Service = {
         await invoke<boolean>('delete_object', {
           endpoint: sanitizedEndpoint,
           region: account.region,
-          accessKeyId: account.accessKeyId.trim(),
-          secretAccessKey: account.secretAccessKey.trim(),
+          accessKeyId: (account.accessKeyId ?? '').trim(),
+          secretAccessKey: (account.secretAccessKey ?? '').trim(),
+          connection: connectionFor(account),
           bucket: account.bucketName,
           key: probeKey,
         });
@@ -478,6 +591,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket: bucketName,
       });
 
@@ -512,6 +626,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket: bucketName,
       });
 
@@ -542,6 +657,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         prefix,
       });
@@ -589,6 +705,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         prefix,
       });
@@ -624,6 +741,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           bucket,
           key,
           body,
@@ -640,6 +758,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           bucket,
           key,
           contentType: file.type || 'application/octet-stream',
@@ -662,6 +781,7 @@ export const s3Service = {
               region: account.region,
               accessKeyId: account.accessKeyId.trim(),
               secretAccessKey: account.secretAccessKey.trim(),
+              connection: connectionFor(account),
               bucket,
               key,
               uploadId,
@@ -683,6 +803,7 @@ export const s3Service = {
             region: account.region,
             accessKeyId: account.accessKeyId.trim(),
             secretAccessKey: account.secretAccessKey.trim(),
+            connection: connectionFor(account),
             bucket,
             key,
             uploadId,
@@ -696,6 +817,7 @@ export const s3Service = {
             region: account.region,
             accessKeyId: account.accessKeyId.trim(),
             secretAccessKey: account.secretAccessKey.trim(),
+            connection: connectionFor(account),
             bucket,
             key,
             uploadId,
@@ -725,6 +847,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         key,
       });
@@ -744,6 +867,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         query,
       });
@@ -778,6 +902,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         folderPath,
       });
@@ -816,6 +941,7 @@ export const s3Service = {
             region: account.region,
             accessKeyId: account.accessKeyId.trim(),
             secretAccessKey: account.secretAccessKey.trim(),
+            connection: connectionFor(account),
             bucket,
             sourceKey: object.key,
             destKey: trashKey,
@@ -831,6 +957,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           bucket,
           key: object.key,
         });
@@ -846,6 +973,7 @@ export const s3Service = {
             region: account.region,
             accessKeyId: account.accessKeyId.trim(),
             secretAccessKey: account.secretAccessKey.trim(),
+            connection: connectionFor(account),
             bucket,
             sourceKey: object.key,
             destKey: trashKey,
@@ -862,6 +990,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           bucket,
           key: object.key,
         });
@@ -897,6 +1026,7 @@ export const s3Service = {
             region: account.region,
             accessKeyId: account.accessKeyId.trim(),
             secretAccessKey: account.secretAccessKey.trim(),
+            connection: connectionFor(account),
             bucket,
             oldKey: obj.key,
             newKey: newObjKey,
@@ -911,6 +1041,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           bucket,
           oldKey: object.key,
           newKey,
@@ -924,6 +1055,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           bucket,
           oldKey: object.key,
           newKey,
@@ -977,6 +1109,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           sourceBucket,
           sourcePrefix: object.key,
           destBucket,
@@ -992,6 +1125,7 @@ export const s3Service = {
           region: account.region,
           accessKeyId: account.accessKeyId.trim(),
           secretAccessKey: account.secretAccessKey.trim(),
+          connection: connectionFor(account),
           sourceBucket,
           sourceKey: object.key,
           destBucket,
@@ -1032,6 +1166,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         key: object.key,
         expiresIn
@@ -1057,6 +1192,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket,
         key
       });
@@ -1088,6 +1224,7 @@ export const s3Service = {
       region: account.region,
       accessKeyId: account.accessKeyId.trim(),
       secretAccessKey: account.secretAccessKey.trim(),
+      connection: connectionFor(account),
       bucket,
       sourceKey: object.key,
       destKey: originalKey,
@@ -1100,6 +1237,7 @@ export const s3Service = {
       region: account.region,
       accessKeyId: account.accessKeyId.trim(),
       secretAccessKey: account.secretAccessKey.trim(),
+      connection: connectionFor(account),
       bucket,
       key: object.key
     });
@@ -1108,6 +1246,7 @@ export const s3Service = {
       region: account.region,
       accessKeyId: account.accessKeyId.trim(),
       secretAccessKey: account.secretAccessKey.trim(),
+      connection: connectionFor(account),
       bucket,
       key: object.key
     });
@@ -1127,6 +1266,7 @@ export const s3Service = {
       region: account.region,
       accessKeyId: account.accessKeyId.trim(),
       secretAccessKey: account.secretAccessKey.trim(),
+      connection: connectionFor(account),
       bucket,
       localPath,
       remotePath,
@@ -1176,6 +1316,7 @@ export const s3Service = {
               region: sourceAcc.region,
               accessKeyId: sourceAcc.accessKeyId,
               secretAccessKey: sourceAcc.secretAccessKey,
+              connection: connectionFor(sourceAcc),
               sourceBucket: job.sourceBucket,
               sourcePrefix: job.sourceKey,
               destBucket: job.destBucket,
@@ -1188,6 +1329,7 @@ export const s3Service = {
               region: sourceAcc.region,
               accessKeyId: sourceAcc.accessKeyId,
               secretAccessKey: sourceAcc.secretAccessKey,
+              connection: connectionFor(sourceAcc),
               sourceBucket: job.sourceBucket,
               sourceKey: job.sourceKey,
               destBucket: job.destBucket,
@@ -1207,6 +1349,7 @@ export const s3Service = {
                 sEndpoint, sRegion: sourceAcc.region, sAccessKey: sourceAcc.accessKeyId, sSecretKey: sourceAcc.secretAccessKey,
                 sBucket: job.sourceBucket, sKey: obj.key,
                 dEndpoint, dRegion: destAcc.region, dAccessKey: destAcc.accessKeyId, dSecretKey: destAcc.secretAccessKey,
+                sConnection: connectionFor(sourceAcc), dConnection: connectionFor(destAcc),
                 dBucket: job.destBucket, dKey: job.destKey + relativeKey
               });
             }
@@ -1216,6 +1359,7 @@ export const s3Service = {
               sEndpoint, sRegion: sourceAcc.region, sAccessKey: sourceAcc.accessKeyId, sSecretKey: sourceAcc.secretAccessKey,
               sBucket: job.sourceBucket, sKey: job.sourceKey,
               dEndpoint, dRegion: destAcc.region, dAccessKey: destAcc.accessKeyId, dSecretKey: destAcc.secretAccessKey,
+              sConnection: connectionFor(sourceAcc), dConnection: connectionFor(destAcc),
               dBucket: job.destBucket, dKey: job.destKey
             });
           }
@@ -1256,96 +1400,66 @@ export const s3Service = {
 
     logSecurityEvent('BUCKET_SCAN_STARTED', { bucket });
     const startTime = Date.now();
-    let totalSize = 0;
-    let totalObjects = 0;
-    const largestFiles: S3Object[] = [];
-    const typeMap = new Map<string, { count: number, size: number }>();
-    const classMap = new Map<string, { count: number, size: number }>();
-    const ageMap = new Map<string, { count: number, size: number }>();
+    const jobId = `analyze-${bucket}-${startTime}`;
 
-    let continuationToken: string | undefined = undefined;
-    const MAX_SCAN = 500000; // Limit for performance in desktop app (500k objects)
+    const unlisten = await listen<any>('analytics-progress', (event) => {
+      if (event.payload.jobId === jobId && onProgress) onProgress(event.payload.objects);
+    });
 
     try {
-      do {
-        const res: any = await safeInvoke('list_objects_recursive', {
-          endpoint: (account.endpoint && account.endpoint.includes('amazonaws.com')) ? '' : account.endpoint,
-          region: account.region,
-          accessKeyId: account.accessKeyId.trim(),
-          secretAccessKey: account.secretAccessKey.trim(),
-          bucket,
-          prefix: '',
-          continuationToken,
-          maxKeys: 1000
-        });
-
-        if (!res.success) throw new Error(res.message);
-
-        for (const obj of res.objects as any[]) {
-          if (obj.is_folder) continue;
-
-          totalObjects++;
-          totalSize += obj.size;
-
-          // Track largest files
-          largestFiles.push({
-            key: obj.key,
-            name: obj.key.split('/').pop() || obj.key,
-            size: obj.size,
-            lastModified: new Date(obj.last_modified).getTime(),
-            type: getFileType(obj.key),
-            isFolder: false
-          });
-          largestFiles.sort((a, b) => b.size - a.size);
-          if (largestFiles.length > 20) largestFiles.pop(); // Show top 20
-
-          // Type distribution
-          const type = getFileType(obj.key);
-          const currentType = typeMap.get(type) || { count: 0, size: 0 };
-          typeMap.set(type, { count: currentType.count + 1, size: currentType.size + obj.size });
-
-          // Storage class distribution
-          const storageClass = obj.storage_class || 'Standard';
-          const currentClass = classMap.get(storageClass) || { count: 0, size: 0 };
-          classMap.set(storageClass, { count: currentClass.count + 1, size: currentClass.size + obj.size });
-
-          // Age distribution
-          const lastModified = new Date(obj.last_modified).getTime();
-          const ageDays = (Date.now() - lastModified) / (1000 * 60 * 60 * 24);
-          let ageRange = '0-30 days';
-          if (ageDays > 365) ageRange = '1+ year';
-          else if (ageDays > 180) ageRange = '180-365 days';
-          else if (ageDays > 90) ageRange = '90-180 days';
-          else if (ageDays > 30) ageRange = '30-90 days';
-
-          const currentAge = ageMap.get(ageRange) || { count: 0, size: 0 };
-          ageMap.set(ageRange, { count: currentAge.count + 1, size: currentAge.size + obj.size });
-        }
-
-        continuationToken = res.next_continuation_token;
-        if (onProgress) onProgress(totalObjects);
-
-      } while (continuationToken && totalObjects < MAX_SCAN);
-
-      const analytics: BucketAnalytics = {
-        totalSize,
-        totalObjects,
-        largestFiles,
-        fileTypeDistribution: Array.from(typeMap.entries()).map(([type, data]) => ({ type, ...data })),
-        storageClassDistribution: Array.from(classMap.entries()).map(([storageClass, data]) => ({ storageClass, ...data })),
-        ageDistribution: Array.from(ageMap.entries()).map(([ageRange, data]) => ({ ageRange, ...data })),
-        lastUpdated: Date.now()
-      };
-
-      logSecurityEvent('BUCKET_SCAN_COMPLETED', { bucket, totalObjects, timeElapsed: Date.now() - startTime });
-      return analytics;
+      const analysis = await safeInvoke('analyze_bucket', {
+        jobId,
+        endpoint: (account.endpoint && account.endpoint.includes('amazonaws.com')) ? '' : account.endpoint,
+        region: account.region,
+        accessKeyId: account.accessKeyId.trim(),
+        secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
+        bucket,
+      });
 
+      logSecurityEvent('BUCKET_SCAN_COMPLETED', { bucket, totalObjects: analysis.total.count, timeElapsed: Date.now() - startTime });
+      return toBucketAnalytics(analysis);
     } catch (error: any) {
       logSecurityEvent('BUCKET_SCAN_FAILED', { bucket, error: error.message });
       throw error;
+    } finally {
+      unlisten();
     }
   },
 
+  // The last stored analysis, without scanning the bucket again
+  getStoredAnalytics: async (account: S3Account, bucket: string, analysisId?: number): Promise<BucketAnalytics | null> => {
+    const analysis = await safeInvoke('get_bucket_analysis', {
+      endpoint: (account.endpoint && account.endpoint.includes('amazonaws.com')) ? '' : account.endpoint,
+      bucket,
+      analysisId: analysisId ?? null,
+    });
+    return analysis ? toBucketAnalytics(analysis) : null;
+  },
+
+  getAnalyticsHistory: async (account: S3Account, bucket: string): Promise<AnalyticsTrendPoint[]> => {
+    const points = await safeInvoke<any[]>('get_bucket_analysis_history', {
+      endpoint: (account.endpoint && account.endpoint.includes('amazonaws.com')) ? '' : account.endpoint,
+      bucket,
+    });
+    return points.map(p => ({
+      id: p.id,
+      analyzedAt: new Date(p.analyzed_at).getTime(),
+      totalObjects: p.object_count,
+      totalSize: p.total_size,
+      incompleteUploadSize: p.incomplete_upload_size,
+    }));
+  },
+
+  exportAnalytics: async (account: S3Account, bucket: string, format: 'json' | 'csv', analysisId?: number): Promise<string> => {
+    return await safeInvoke<string>('export_bucket_analysis', {
+      endpoint: (account.endpoint && account.endpoint.includes('amazonaws.com')) ? '' : account.endpoint,
+      bucket,
+      analysisId: analysisId ?? null,
+      format,
+    });
+  },
+
   logActivity: async (entry: any): Promise<void> => {
     try {
       await invoke('log_activity_entry', { entry });
@@ -1362,6 +1476,7 @@ export const s3Service = {
         region: account.region,
         accessKeyId: account.accessKeyId.trim(),
         secretAccessKey: account.secretAccessKey.trim(),
+        connection: connectionFor(account),
         bucket: account.bucketName,
       });
       return success;
@@ -0,0 +1,199 @@
+use crate::connection::ConnectionOptions;
+use aws_config::profile::{Profile, ProfileSet};
+use aws_runtime::env_config::file::EnvConfigFiles;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+const FALLBACK_REGION: &str = "us-east-1";
+
+// A profile from ~/.aws/config or ~/.aws/credentials, without any secrets
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AwsProfile {
+    pub name: String,
+    // "static", "assume_role", "web_identity", "sso", "credential_process" or "unknown"
+    pub kind: String,
+    pub region: Option<String>,
+    pub endpoint_url: Option<String>,
+    pub role_arn: Option<String>,
+    pub source_profile: Option<String>,
+    pub sso_start_url: Option<String>,
+    pub sso_account_id: Option<String>,
+    pub sso_role_name: Option<String>,
+    // The profile the CLI would use (AWS_PROFILE or "default")
+    pub is_selected: bool,
+}
+
+// What the frontend needs to save a connection backed by a profile
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfileConnection {
+    pub name: String,
+    pub endpoint: String,
+    pub region: String,
+    pub connection: ConnectionOptions,
+    // Set when `verify` was requested and the profile didn't resolve
+    pub error: Option<String>,
+}
+
+// `selected` overrides AWS_PROFILE. The file system and environment are the
+// real ones; only the SDK's own tests substitute them.
+async fn load_profiles(files: &EnvConfigFiles, selected: Option<String>) -> Result<ProfileSet, String> {
+    aws_config::profile::load(&Default::default(), &Default::default(), files, selected.map(Cow::Owned))
+        .await
+        .map_err(|e| format!("Failed to read AWS config files: {}", e))
+}
+
+// Same precedence the SDK profile provider uses when a profile sets several
+fn profile_kind(profile: &Profile) -> &'static str {
+    if profile.get("role_arn").is_some() {
+        if profile.get("web_identity_token_file").is_some() {
+            "web_identity"
+        } else {
+            "assume_role"
+        }
+    } else if profile.get("sso_session").is_some() || profile.get("sso_start_url").is_some() {
+        "sso"
+    } else if profile.get("credential_process").is_some() {
+        "credential_process"
+    } else if profile.get("aws_access_key_id").is_some() {
+        "static"
+    } else {
+        "unknown"
+    }
+}
+
+// Region of the profile, falling back along the source_profile chain
+fn profile_region(profiles: &ProfileSet, name: &str) -> Option<String> {
+    let mut current = profiles.get_profile(name);
+    let mut hops = 0;
+    while let Some(profile) = current {
+        if let Some(region) = profile.get("region") {
+            return Some(region.to_string());
+        }
+        hops += 1;
+        if hops > 10 {
+            break;
+        }
+        current = profile.get("source_profile").and_then(|p| profiles.get_profile(p));
+    }
+    None
+}
+
+fn summarize(profiles: &ProfileSet) -> Vec<AwsProfile> {
+    let mut names: Vec<&str> = profiles.profiles().collect();
+    names.sort();
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let profile = profiles.get_profile(name)?;
+            // sso_start_url and friends may live in a shared [sso-session] block
+            let sso_session = profile.get("sso_session").and_then(|s| profiles.sso_session(s));
+            let sso_value = |key: &str| {
+                profile
+                    .get(key)
+                    .or_else(|| sso_session.and_then(|s| s.get(key)))
+                    .map(|v| v.to_string())
+            };
+
+            Some(AwsProfile {
+                name: name.to_string(),
+                kind: profile_kind(profile).to_string(),
+                region: profile_region(profiles, name),
+                endpoint_url: profile.get("endpoint_url").map(|v| v.to_string()),
+                role_arn: profile.get("role_arn").map(|v| v.to_string()),
+                source_profile: profile.get("source_profile").map(|v| v.to_string()),
+                sso_start_url: sso_value("sso_start_url"),
+                sso_account_id: sso_value("sso_account_id"),
+                sso_role_name: sso_value("sso_role_name"),
+                is_selected: name == profiles.selected_profile(),
+            })
+        })
+        .collect()
+}
+
+pub async fn list_profiles() -> Result<Vec<AwsProfile>, String> {
+    let profiles = load_profiles(&EnvConfigFiles::default(), None).await?;
+    Ok(summarize(&profiles))
+}
+
+/// Connection drafts for the given profiles (all of them when `names` is None).
+/// Keys are never copied out of the AWS files: the connection keeps resolving
+/// through the profile, so rotations and SSO logins in the CLI carry over.
+pub async fn profile_connections(names: Option<Vec<String>>, verify: bool) -> Result<Vec<ProfileConnection>, String> {
+    let profiles = list_profiles().await?;
+    let mut result = Vec::new();
+
+    for profile in profiles {
+        if let Some(names) = &names {
+            if !names.contains(&profile.name) {
+                continue;
+            }
+        }
+
+        let region = profile.region.clone().unwrap_or_else(|| FALLBACK_REGION.to_string());
+        let connection = ConnectionOptions {
+            profile: Some(profile.name.clone()),
+            ..Default::default()
+        };
+
+        let error = if verify {
+            crate::connection::resolve_credentials("", "", &region, Some(&connection))
+                .await
+                .err()
+        } else {
+            None
+        };
+
+        result.push(ProfileConnection {
+            name: profile.name,
+            endpoint: profile.endpoint_url.unwrap_or_default(),
+            region,
+            connection,
+            error,
+        });
+    }
+
+    if let Some(names) = names {
+        if let Some(missing) = names.iter().find(|n| !result.iter().any(|c| &c.name == *n)) {
+            return Err(format!("AWS profile '{}' not found", missing));
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_summarizes_profile_kinds() {
+        use aws_runtime::env_config::file::EnvConfigFileKind;
+        let files = EnvConfigFiles::builder()
+            .with_contents(
+                EnvConfigFileKind::Config,
+                "[default]\nregion = eu-west-1\n\n\
+                 [profile deploy]\nrole_arn = arn:aws:iam::123456789012:role/Deploy\nsource_profile = default\n\n\
+                 [profile dev]\nsso_session = corp\nsso_account_id = 111122223333\nsso_role_name = Dev\nregion = us-west-2\n\n\
+                 [profile vault]\ncredential_process = aws-vault export --format=json work\n\n\
+                 [sso-session corp]\nsso_start_url = https://corp.awsapps.com/start\nsso_region = us-east-1\n",
+            )
+            .with_contents(
+                EnvConfigFileKind::Credentials,
+                "[default]\naws_access_key_id = AKID\naws_secret_access_key = SECRET\n",
+            )
+            .build();
+
+        let profiles = summarize(&load_profiles(&files, Some("default".to_string())).await.unwrap());
+        let get = |name: &str| profiles.iter().find(|p| p.name == name).unwrap();
+
+        assert_eq!(get("default").kind, "static");
+        assert!(get("default").is_selected);
+        assert_eq!(get("deploy").kind, "assume_role");
+        // Inherited from the source profile
+        assert_eq!(get("deploy").region.as_deref(), Some("eu-west-1"));
+        assert_eq!(get("dev").kind, "sso");
+        assert_eq!(get("dev").sso_start_url.as_deref(), Some("https://corp.awsapps.com/start"));
+        assert_eq!(get("vault").kind, "credential_process");
+    }
+}
@@ -0,0 +1,109 @@
+use aws_sdk_s3::Client as S3Client;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// A bucket's region can't change while it exists, so discoveries are kept for
+// the session and only dropped when we delete the bucket ourselves
+static REGIONS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// Failed lookups (missing bucket, no permission), so commands in the meantime
+// use the connection's region instead of repeating two requests each
+static FAILED: Lazy<Mutex<HashMap<String, Instant>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+const FAILED_LOOKUP_TTL: Duration = Duration::from_secs(60);
+
+fn cache_key(endpoint: &str, bucket: &str) -> String {
+    format!("{}|{}", endpoint.trim().trim_end_matches('/'), bucket)
+}
+
+pub fn cached(endpoint: &str, bucket: &str) -> Option<String> {
+    REGIONS.lock().unwrap().get(&cache_key(endpoint, bucket)).cloned()
+}
+
+pub fn remember(endpoint: &str, bucket: &str, region: &str) {
+    FAILED.lock().unwrap().remove(&cache_key(endpoint, bucket));
+    REGIONS.lock().unwrap().insert(cache_key(endpoint, bucket), region.to_string());
+}
+
+pub fn remember_failure(endpoint: &str, bucket: &str) {
+    FAILED.lock().unwrap().insert(cache_key(endpoint, bucket), Instant::now());
+}
+
+/// A lookup failed less than `FAILED_LOOKUP_TTL` ago
+pub fn recently_failed(endpoint: &str, bucket: &str) -> bool {
+    let mut failed = FAILED.lock().unwrap();
+    let key = cache_key(endpoint, bucket);
+    match failed.get(&key) {
+        Some(at) if at.elapsed() < FAILED_LOOKUP_TTL => true,
+        Some(_) => {
+            failed.remove(&key);
+            false
+        }
+        None => false,
+    }
+}
+
+pub fn forget(endpoint: &str, bucket: &str) {
+    FAILED.lock().unwrap().remove(&cache_key(endpoint, bucket));
+    REGIONS.lock().unwrap().remove(&cache_key(endpoint, bucket));
+}
+
+// GetBucketLocation reports us-east-1 as no constraint and eu-west-1 as the legacy "EU"
+fn normalize_location(location: &str) -> String {
+    match location {
+        "" => "us-east-1".to_string(),
+        "EU" => "eu-west-1".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Ask AWS where `bucket` lives. HeadBucket reports it in `x-amz-bucket-region`
+/// even when it fails with a redirect or access denied; GetBucketLocation is
+/// the fallback for callers that may not call HeadBucket.
+pub async fn discover(client: &S3Client, bucket: &str) -> Option<String> {
+    match client.head_bucket().bucket(bucket).send().await {
+        Ok(output) => {
+            if let Some(region) = output.bucket_region() {
+                return Some(region.to_string());
+            }
+        }
+        Err(e) => {
+            let header = e
+                .raw_response()
+                .and_then(|r| r.headers().get("x-amz-bucket-region"))
+                .map(str::to_string);
+            if header.is_some() {
+                return header;
+            }
+        }
+    }
+
+    let location = client.get_bucket_location().bucket(bucket).send().await.ok()?;
+    Some(normalize_location(
+        location.location_constraint().map(|c| c.as_str()).unwrap_or(""),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_and_location_normalization() {
+        assert_eq!(normalize_location(""), "us-east-1");
+        assert_eq!(normalize_location("EU"), "eu-west-1");
+        assert_eq!(normalize_location("ap-south-1"), "ap-south-1");
+
+        remember("https://s3.amazonaws.com/", "logs", "eu-central-1");
+        assert_eq!(cached("https://s3.amazonaws.com", "logs").as_deref(), Some("eu-central-1"));
+        forget("https://s3.amazonaws.com", "logs");
+        assert_eq!(cached("https://s3.amazonaws.com", "logs"), None);
+
+        remember_failure("https://s3.amazonaws.com", "private");
+        assert!(recently_failed("https://s3.amazonaws.com/", "private"));
+        assert!(!recently_failed("https://s3.amazonaws.com", "logs"));
+        // A later success (or deleting / creating the bucket) clears it
+        remember("https://s3.amazonaws.com", "private", "us-west-2");
+        assert!(!recently_failed("https://s3.amazonaws.com", "private"));
+    }
+}
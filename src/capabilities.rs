@@ -0,0 +1,434 @@
+use aws_sdk_s3::config::{Builder as S3ConfigBuilder, RequestChecksumCalculation, ResponseChecksumValidation};
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier, Tag, Tagging};
+use aws_sdk_s3::Client as S3Client;
+use chrono::Utc;
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::error::{BucketStackError, ErrorKind};
+
+const CAPABILITIES_FILE: &str = "capabilities.json";
+// Probe objects live here and are deleted before the probe returns
+const PROBE_PREFIX: &str = ".bucketstack-probe/";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Versioning,
+    Tagging,
+    Lifecycle,
+    // Flexible checksums (x-amz-checksum-*), sent by the SDK by default
+    Checksums,
+    // UploadPartCopy, needed to copy objects over 5 GiB
+    MultipartCopy,
+    DeleteObjects,
+    Presign,
+}
+
+impl Capability {
+    fn label(self) -> &'static str {
+        match self {
+            Capability::Versioning => "Bucket versioning",
+            Capability::Tagging => "Object tagging",
+            Capability::Lifecycle => "Lifecycle rules",
+            Capability::Checksums => "Checksum headers",
+            Capability::MultipartCopy => "Multipart copy",
+            Capability::DeleteObjects => "Batch delete",
+            Capability::Presign => "Presigned URLs",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Support {
+    Supported,
+    Unsupported,
+    // Denied, needs write access or failed for an unrelated reason
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CapabilityResult {
+    pub support: Support,
+    // Why, for anything but a plain success
+    pub detail: Option<String>,
+}
+
+/// What a provider supports, as seen from one bucket
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CapabilityMatrix {
+    pub endpoint: String,
+    // Connection that ran the probe; results can depend on its permissions
+    #[serde(default)]
+    pub connection_key: String,
+    pub bucket: String,
+    pub probed_at: String,
+    pub capabilities: BTreeMap<Capability, CapabilityResult>,
+}
+
+static MATRICES: Lazy<RwLock<HashMap<String, CapabilityMatrix>>> = Lazy::new(|| RwLock::new(load_matrices()));
+
+fn endpoint_key(endpoint: &str) -> String {
+    endpoint.trim().trim_end_matches('/').to_string()
+}
+
+fn matrix_key(endpoint: &str, bucket: &str) -> String {
+    format!("{}|{}", endpoint_key(endpoint), bucket)
+}
+
+fn get_capabilities_path() -> PathBuf {
+    match ProjectDirs::from("com", "bucketstack", "app") {
+        Some(proj_dirs) => proj_dirs.config_dir().join(CAPABILITIES_FILE),
+        None => PathBuf::from(CAPABILITIES_FILE),
+    }
+}
+
+fn load_matrices() -> HashMap<String, CapabilityMatrix> {
+    let matrices: Vec<CapabilityMatrix> = fs::read_to_string(get_capabilities_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    matrices.into_iter().map(|m| (matrix_key(&m.endpoint, &m.bucket), m)).collect()
+}
+
+fn save_matrices(matrices: &HashMap<String, CapabilityMatrix>) {
+    let path = get_capabilities_path();
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            let list: Vec<&CapabilityMatrix> = matrices.values().collect();
+            fs::write(&path, serde_json::to_string_pretty(&list).unwrap_or_default())
+        });
+    // Only a cache; the next probe rebuilds it
+    if let Err(e) = result {
+        eprintln!("Failed to save provider capabilities: {}", e);
+    }
+}
+
+/// The stored matrix for `bucket`, or the latest one the same connection probed
+/// on another bucket of the endpoint since most quirks are provider-wide
+pub fn get(endpoint: &str, connection_key: Option<&str>, bucket: Option<&str>) -> Option<CapabilityMatrix> {
+    find(&MATRICES.read().unwrap(), endpoint, connection_key, bucket)
+}
+
+fn find(
+    matrices: &HashMap<String, CapabilityMatrix>,
+    endpoint: &str,
+    connection_key: Option<&str>,
+    bucket: Option<&str>,
+) -> Option<CapabilityMatrix> {
+    if let Some(matrix) = bucket.and_then(|b| matrices.get(&matrix_key(endpoint, b))) {
+        return Some(matrix.clone());
+    }
+    // Another account on the same endpoint may see different results
+    let connection_key = connection_key?;
+    let endpoint = endpoint_key(endpoint);
+    matrices
+        .values()
+        .filter(|m| endpoint_key(&m.endpoint) == endpoint && m.connection_key == connection_key)
+        .max_by(|a, b| a.probed_at.cmp(&b.probed_at))
+        .cloned()
+}
+
+pub fn store(matrix: CapabilityMatrix) {
+    let mut matrices = MATRICES.write().unwrap();
+    matrices.insert(matrix_key(&matrix.endpoint, &matrix.bucket), matrix);
+    save_matrices(&matrices);
+}
+
+/// Note a capability found missing outside a probe, e.g. after a fallback
+pub fn record_unsupported(endpoint: &str, connection_key: &str, bucket: &str, capability: Capability, detail: String) {
+    let mut matrix = get(endpoint, Some(connection_key), Some(bucket))
+        .filter(|m| m.bucket == bucket)
+        .unwrap_or_else(|| CapabilityMatrix {
+            endpoint: endpoint.to_string(),
+            connection_key: connection_key.to_string(),
+            bucket: bucket.to_string(),
+            probed_at: Utc::now().to_rfc3339(),
+            capabilities: BTreeMap::new(),
+        });
+    matrix
+        .capabilities
+        .insert(capability, CapabilityResult { support: Support::Unsupported, detail: Some(detail) });
+    store(matrix);
+}
+
+/// False only when the provider is known not to support `capability`;
+/// anything unprobed is assumed to work
+pub fn supports(endpoint: &str, connection_key: &str, bucket: Option<&str>, capability: Capability) -> bool {
+    get(endpoint, Some(connection_key), bucket)
+        .and_then(|m| m.capabilities.get(&capability).map(|r| r.support))
+        != Some(Support::Unsupported)
+}
+
+pub fn require(endpoint: &str, connection_key: &str, bucket: &str, capability: Capability) -> Result<(), BucketStackError> {
+    if supports(endpoint, connection_key, Some(bucket), capability) {
+        return Ok(());
+    }
+    Err(BucketStackError::new(
+        ErrorKind::Unsupported,
+        format!("{} is not supported by this provider", capability.label()),
+    ))
+}
+
+/// Only send checksums the operation requires, for providers that reject
+/// the SDK's default CRC32 headers
+pub fn without_checksums(builder: S3ConfigBuilder) -> S3ConfigBuilder {
+    builder
+        .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+        .response_checksum_validation(ResponseChecksumValidation::WhenRequired)
+}
+
+fn outcome<T>(result: Result<T, BucketStackError>) -> CapabilityResult {
+    match result {
+        Ok(_) => CapabilityResult { support: Support::Supported, detail: None },
+        // The probes are well-formed, so a rejected request means the feature is missing
+        Err(e) if matches!(e.kind, ErrorKind::Unsupported | ErrorKind::InvalidRequest) => {
+            CapabilityResult { support: Support::Unsupported, detail: Some(e.message) }
+        }
+        Err(e) => CapabilityResult { support: Support::Unknown, detail: Some(e.message) },
+    }
+}
+
+fn needs_write(detail: &str) -> CapabilityResult {
+    CapabilityResult {
+        support: Support::Unknown,
+        detail: Some(format!("Could not write a probe object: {}", detail)),
+    }
+}
+
+/// Run harmless calls against `bucket` to see what the provider supports.
+/// Writes one small object under `.bucketstack-probe/` and removes it again.
+pub async fn probe(
+    client: &S3Client,
+    http: &reqwest::Client,
+    endpoint: &str,
+    connection_key: &str,
+    bucket: &str,
+) -> CapabilityMatrix {
+    let mut results = BTreeMap::new();
+    let key = format!("{}{}", PROBE_PREFIX, Utc::now().timestamp_nanos_opt().unwrap_or_default());
+    let copy_key = format!("{}.copy", key);
+
+    // Explicitly on, whatever an earlier probe decided for this endpoint
+    let checked = S3Client::from_conf(
+        client
+            .config()
+            .to_builder()
+            .request_checksum_calculation(RequestChecksumCalculation::WhenSupported)
+            .response_checksum_validation(ResponseChecksumValidation::WhenSupported)
+            .build(),
+    );
+    let unchecked = S3Client::from_conf(without_checksums(client.config().to_builder()).build());
+
+    let put = |client: S3Client| {
+        let key = key.clone();
+        async move {
+            client
+                .put_object()
+                .bucket(bucket)
+                .key(&key)
+                .body(ByteStream::from_static(b"bucketstack capability probe"))
+                .send()
+                .await
+                .map_err(BucketStackError::from)
+        }
+    };
+    let written = match put(checked.clone()).await {
+        Ok(_) => {
+            results.insert(Capability::Checksums, outcome(Ok(())));
+            Ok(())
+        }
+        Err(with_checksums) => match put(unchecked.clone()).await {
+            Ok(_) => {
+                // Only the checksum headers differed, so they were the problem
+                results.insert(
+                    Capability::Checksums,
+                    CapabilityResult { support: Support::Unsupported, detail: Some(with_checksums.message) },
+                );
+                Ok(())
+            }
+            Err(e) => {
+                results.insert(Capability::Checksums, needs_write(&e.message));
+                Err(e.message)
+            }
+        },
+    };
+    // Everything after this uses whichever client managed the write
+    let client = match results.get(&Capability::Checksums).map(|r| r.support) {
+        Some(Support::Unsupported) => unchecked,
+        _ => checked,
+    };
+
+    results.insert(
+        Capability::Versioning,
+        outcome(client.get_bucket_versioning().bucket(bucket).send().await.map_err(BucketStackError::from)),
+    );
+
+    let lifecycle = client.get_bucket_lifecycle_configuration().bucket(bucket).send().await;
+    results.insert(
+        Capability::Lifecycle,
+        match lifecycle.map_err(BucketStackError::from) {
+            // A bucket without rules still has the API
+            Err(e) if e.code.as_deref() == Some("NoSuchLifecycleConfiguration") => outcome(Ok(())),
+            other => outcome(other),
+        },
+    );
+
+    results.insert(Capability::Presign, probe_presign(&client, http, bucket, &key).await);
+
+    match &written {
+        Ok(()) => {
+            let tagging = Tagging::builder()
+                .tag_set(Tag::builder().key("bucketstack-probe").value("1").build().expect("key and value are set"))
+                .build()
+                .expect("tag set is set");
+            let tagged = client
+                .put_object_tagging()
+                .bucket(bucket)
+                .key(&key)
+                .tagging(tagging)
+                .send()
+                .await
+                .map_err(BucketStackError::from);
+            results.insert(Capability::Tagging, outcome(tagged));
+            results.insert(Capability::MultipartCopy, outcome(probe_multipart_copy(&client, bucket, &key, &copy_key).await));
+
+            let objects: Vec<ObjectIdentifier> = [&key, &copy_key]
+                .iter()
+                .filter_map(|k| ObjectIdentifier::builder().key(k.as_str()).build().ok())
+                .collect();
+            let deleted = match Delete::builder().set_objects(Some(objects)).quiet(true).build() {
+                Ok(delete) => client
+                    .delete_objects()
+                    .bucket(bucket)
+                    .delete(delete)
+                    .send()
+                    .await
+                    .map_err(BucketStackError::from),
+                Err(e) => Err(BucketStackError::wrap("Failed to build delete request", e)),
+            };
+            if deleted.is_err() {
+                for k in [&key, &copy_key] {
+                    let _ = client.delete_object().bucket(bucket).key(k).send().await;
+                }
+            }
+            results.insert(Capability::DeleteObjects, outcome(deleted));
+        }
+        Err(detail) => {
+            for capability in [Capability::Tagging, Capability::MultipartCopy, Capability::DeleteObjects] {
+                results.insert(capability, needs_write(detail));
+            }
+        }
+    }
+
+    CapabilityMatrix {
+        endpoint: endpoint.to_string(),
+        connection_key: connection_key.to_string(),
+        bucket: bucket.to_string(),
+        probed_at: Utc::now().to_rfc3339(),
+        capabilities: results,
+    }
+}
+
+async fn probe_presign(client: &S3Client, http: &reqwest::Client, bucket: &str, key: &str) -> CapabilityResult {
+    let config = match PresigningConfig::expires_in(Duration::from_secs(60)) {
+        Ok(config) => config,
+        Err(e) => return outcome::<()>(Err(BucketStackError::wrap("Failed to presign", e))),
+    };
+    let request = match client.get_object().bucket(bucket).key(key).presigned(config).await {
+        Ok(request) => request,
+        Err(e) => return outcome::<()>(Err(BucketStackError::wrap("Failed to presign", e))),
+    };
+    let response = match http.get(request.uri()).send().await {
+        Ok(response) => response,
+        Err(e) => return outcome::<()>(Err(BucketStackError::new(ErrorKind::Network, e.to_string()))),
+    };
+
+    let status = response.status().as_u16();
+    // A missing probe object still proves the signature was accepted
+    if status == 200 || status == 404 {
+        return outcome(Ok(()));
+    }
+    let body = response.text().await.unwrap_or_default();
+    let rejected_signature = ["SignatureDoesNotMatch", "AuthorizationQueryParametersError", "NotImplemented"]
+        .iter()
+        .any(|code| body.contains(code));
+    CapabilityResult {
+        support: if rejected_signature || status == 400 { Support::Unsupported } else { Support::Unknown },
+        detail: Some(format!("Presigned GET returned HTTP {}", status)),
+    }
+}
+
+async fn probe_multipart_copy(client: &S3Client, bucket: &str, key: &str, copy_key: &str) -> Result<(), BucketStackError> {
+    let upload = client.create_multipart_upload().bucket(bucket).key(copy_key).send().await?;
+    let upload_id = upload.upload_id().unwrap_or_default();
+
+    let copied = client
+        .upload_part_copy()
+        .bucket(bucket)
+        .key(copy_key)
+        .upload_id(upload_id)
+        .part_number(1)
+        .copy_source(format!("{}/{}", bucket, urlencoding::encode(key)))
+        .send()
+        .await;
+    // Only the part copy matters; never leave the upload (or its parts) behind
+    let _ = client.abort_multipart_upload().bucket(bucket).key(copy_key).upload_id(upload_id).send().await;
+    copied.map(|_| ()).map_err(BucketStackError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_classification() {
+        assert_eq!(outcome::<()>(Ok(())).support, Support::Supported);
+
+        let mut not_implemented = BucketStackError::new(ErrorKind::Unsupported, "NotImplemented");
+        not_implemented.http_status = Some(501);
+        assert_eq!(outcome::<()>(Err(not_implemented)).support, Support::Unsupported);
+
+        let denied = BucketStackError::new(ErrorKind::AccessDenied, "AccessDenied");
+        let result = outcome::<()>(Err(denied));
+        assert_eq!(result.support, Support::Unknown);
+        assert_eq!(result.detail.as_deref(), Some("AccessDenied"));
+    }
+
+    #[test]
+    fn test_fallback_stays_within_connection() {
+        let matrix = |connection_key: &str, bucket: &str, probed_at: &str| CapabilityMatrix {
+            endpoint: "https://s3.example.com".to_string(),
+            connection_key: connection_key.to_string(),
+            bucket: bucket.to_string(),
+            probed_at: probed_at.to_string(),
+            capabilities: BTreeMap::new(),
+        };
+        let matrices: HashMap<String, CapabilityMatrix> = [
+            matrix("alice", "photos", "2026-01-01T00:00:00Z"),
+            matrix("bob", "backups", "2026-02-01T00:00:00Z"),
+        ]
+        .into_iter()
+        .map(|m| (matrix_key(&m.endpoint, &m.bucket), m))
+        .collect();
+        let endpoint = "https://s3.example.com/";
+
+        // The bucket's own matrix wins whoever probed it
+        assert_eq!(find(&matrices, endpoint, Some("bob"), Some("photos")).unwrap().connection_key, "alice");
+        // Other buckets only stand in for the same connection, however recent
+        assert_eq!(find(&matrices, endpoint, Some("alice"), Some("music")).unwrap().bucket, "photos");
+        assert!(find(&matrices, endpoint, Some("carol"), Some("music")).is_none());
+        assert!(find(&matrices, endpoint, None, Some("music")).is_none());
+    }
+}
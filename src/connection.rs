@@ -0,0 +1,410 @@
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_config::profile::ProfileFileCredentialsProvider;
+use aws_config::provider_config::ProviderConfig;
+use aws_config::BehaviorVersion;
+use aws_credential_types::provider::{error::CredentialsError, future, ProvideCredentials, SharedCredentialsProvider};
+use aws_credential_types::Credentials;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::endpoint::AddressingStyle;
+use crate::network::{self, NetworkPolicy, ProxySettings, TlsSettings};
+
+// Refresh role sessions this long before STS says they expire
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+// Credentials without an expiry (profile keys, credential_process output
+// that omits one) are re-read this often, so edits to ~/.aws are picked up
+const UNEXPIRING_TTL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_SESSION_NAME: &str = "BucketStack";
+
+// Per-connection settings sent by the frontend alongside endpoint/region/keys.
+// Everything is optional so older callers keep working unchanged.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ConnectionOptions {
+    // The frontend's id for the connection, used to group request metrics
+    pub connection_id: Option<String>,
+    // Session token for temporary (STS) access keys
+    pub session_token: Option<String>,
+    // Assume this role using the connection's keys
+    pub assume_role: Option<AssumeRoleConfig>,
+    // Assume a role with an OIDC token instead of keys
+    pub web_identity: Option<WebIdentityConfig>,
+    // Resolve keys through this ~/.aws profile (source_profile, role_arn,
+    // credential_process, SSO) instead of using stored keys
+    pub profile: Option<String>,
+    // Resolve keys through the SDK default chain: environment, profile, SSO,
+    // container and instance metadata
+    #[serde(default)]
+    pub use_default_chain: bool,
+    // Retries, timeouts and connection pooling
+    pub network: Option<NetworkPolicy>,
+    pub proxy: Option<ProxySettings>,
+    // Custom CA, client certificate (mTLS), skip verification
+    pub tls: Option<TlsSettings>,
+    #[serde(default)]
+    pub addressing_style: AddressingStyle,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AssumeRoleConfig {
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    pub session_name: Option<String>,
+    pub duration_seconds: Option<i32>,
+    // Virtual/hardware MFA device; `mfa_token` is the current code. A code is
+    // only needed when a new session has to be created, so MFA sessions can't
+    // refresh on their own once they expire.
+    pub mfa_serial: Option<String>,
+    #[serde(skip_serializing)]
+    pub mfa_token: Option<String>,
+    // STS endpoint for S3-compatible servers with their own STS (e.g. MinIO)
+    pub sts_endpoint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WebIdentityConfig {
+    pub role_arn: String,
+    // Either the token itself or a file it is (re)written to by an external tool
+    pub token: Option<String>,
+    pub token_file: Option<String>,
+    pub session_name: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub sts_endpoint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoleSessionInfo {
+    pub access_key_id: String,
+    pub expires_at: Option<String>,
+}
+
+// Role sessions and profile/chain credentials outlive the per-command S3
+// clients, so they are cached globally. Each key has its own tokio mutex so
+// concurrent commands wait for one STS call (or credential_process run)
+// instead of racing, without a slow provider holding up other connections.
+// Slots hold the credentials and when to fetch them again.
+type SessionSlot = Arc<tokio::sync::Mutex<Option<(Credentials, SystemTime)>>>;
+static SESSIONS: Lazy<Mutex<HashMap<String, SessionSlot>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Stable id for a connection in local data (metrics, listing cache): the
+/// frontend's connection id when it sends one, otherwise key and endpoint
+pub fn connection_key(options: Option<&ConnectionOptions>, endpoint: &str, access_key_id: &str) -> String {
+    match options.and_then(|o| o.connection_id.as_deref()).filter(|id| !id.is_empty()) {
+        Some(id) => id.to_string(),
+        None if endpoint.trim().is_empty() => access_key_id.to_string(),
+        None => format!("{}@{}", access_key_id, endpoint.trim().trim_end_matches('/')),
+    }
+}
+
+pub fn static_credentials(
+    access_key_id: &str,
+    secret_access_key: &str,
+    options: Option<&ConnectionOptions>,
+) -> Credentials {
+    let session_token = options
+        .and_then(|o| o.session_token.as_deref())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty());
+
+    Credentials::new(
+        access_key_id.trim().to_string(),
+        secret_access_key.trim().to_string(),
+        session_token,
+        None,
+        "BucketStack",
+    )
+}
+
+/// Credentials provider for a connection: plain keys, an ~/.aws profile or the
+/// default chain, optionally followed by an STS role session that is assumed
+/// lazily and refreshed shortly before it expires
+pub fn credentials_provider(
+    access_key_id: &str,
+    secret_access_key: &str,
+    region: &str,
+    options: Option<&ConnectionOptions>,
+) -> SharedCredentialsProvider {
+    let source = match options {
+        Some(o) if o.profile.as_deref().is_some_and(|p| !p.is_empty()) => {
+            CredentialSource::Profile(o.profile.clone().unwrap_or_default())
+        }
+        Some(o) if o.use_default_chain => CredentialSource::DefaultChain,
+        _ => CredentialSource::Static(static_credentials(access_key_id, secret_access_key, options)),
+    };
+
+    let needs_session = options.is_some_and(|o| o.assume_role.is_some() || o.web_identity.is_some());
+
+    match source {
+        CredentialSource::Static(creds) if !needs_session => SharedCredentialsProvider::new(creds),
+        source => SharedCredentialsProvider::new(ConnectionCredentialsProvider {
+            source,
+            region: region.to_string(),
+            options: options.cloned().unwrap_or_default(),
+        }),
+    }
+}
+
+/// Resolve credentials up front, for callers that sign requests themselves
+pub async fn resolve_credentials(
+    access_key_id: &str,
+    secret_access_key: &str,
+    region: &str,
+    options: Option<&ConnectionOptions>,
+) -> Result<Credentials, String> {
+    credentials_provider(access_key_id, secret_access_key, region, options)
+        .provide_credentials()
+        .await
+        .map_err(|e| format!("Failed to resolve credentials: {}", e))
+}
+
+/// Assume the connection's role now (or reuse the cached session) and report
+/// when it expires, so the UI can prompt for an MFA code ahead of time
+pub async fn session_info(
+    access_key_id: &str,
+    secret_access_key: &str,
+    region: &str,
+    options: Option<&ConnectionOptions>,
+) -> Result<RoleSessionInfo, String> {
+    let creds = resolve_credentials(access_key_id, secret_access_key, region, options).await?;
+    Ok(RoleSessionInfo {
+        access_key_id: creds.access_key_id().to_string(),
+        expires_at: creds
+            .expiry()
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+    })
+}
+
+// Forget cached role sessions, e.g. after a connection's role settings change
+pub async fn clear_role_sessions() {
+    SESSIONS.lock().unwrap().clear();
+}
+
+#[derive(Debug)]
+enum CredentialSource {
+    Static(Credentials),
+    Profile(String),
+    DefaultChain,
+}
+
+#[derive(Debug)]
+struct ConnectionCredentialsProvider {
+    source: CredentialSource,
+    region: String,
+    options: ConnectionOptions,
+}
+
+impl ConnectionCredentialsProvider {
+    fn cache_key(&self, with_role: bool) -> String {
+        let mut hasher = Sha256::new();
+        match &self.source {
+            CredentialSource::Static(creds) => hasher.update(format!("keys:{}", creds.access_key_id())),
+            CredentialSource::Profile(name) => hasher.update(format!("profile:{}", name)),
+            CredentialSource::DefaultChain => hasher.update("default-chain"),
+        }
+        hasher.update(self.region.as_bytes());
+        if with_role {
+            hasher.update(format!("{:?}", self.options.assume_role.as_ref().map(|r| (&r.role_arn, &r.external_id, &r.session_name, &r.duration_seconds, &r.mfa_serial, &r.sts_endpoint))).as_bytes());
+            hasher.update(format!("{:?}", self.options.web_identity).as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    async fn resolve(&self) -> Result<Credentials, CredentialsError> {
+        if let Some(web) = &self.options.web_identity {
+            return cached(self.cache_key(true), self.assume_role_with_web_identity(web)).await;
+        }
+
+        let base = match &self.source {
+            CredentialSource::Static(creds) => creds.clone(),
+            _ => cached(self.cache_key(false), self.source_credentials()).await?,
+        };
+
+        match &self.options.assume_role {
+            Some(role) => cached(self.cache_key(true), self.assume_role(&base, role)).await,
+            None => Ok(base),
+        }
+    }
+
+    // Keys from the profile or default chain; the SDK providers handle
+    // source_profile, role_arn, credential_process and the SSO token cache
+    async fn source_credentials(&self) -> Result<Credentials, CredentialsError> {
+        let region = aws_config::Region::new(self.region.clone());
+        match &self.source {
+            CredentialSource::Static(creds) => Ok(creds.clone()),
+            CredentialSource::Profile(name) => {
+                let config = ProviderConfig::without_region().with_region(Some(region));
+                ProfileFileCredentialsProvider::builder()
+                    .configure(&config)
+                    .profile_name(name)
+                    .build()
+                    .provide_credentials()
+                    .await
+            }
+            CredentialSource::DefaultChain => {
+                DefaultCredentialsChain::builder()
+                    .region(region)
+                    .build()
+                    .await
+                    .provide_credentials()
+                    .await
+            }
+        }
+    }
+
+    async fn sts_client(
+        &self,
+        sts_endpoint: Option<&str>,
+        base: Option<&Credentials>,
+    ) -> Result<aws_sdk_sts::Client, CredentialsError> {
+        let mut loader = aws_config::defaults(BehaviorVersion::latest())
+            .region(aws_config::Region::new(self.region.clone()));
+        loader = match base {
+            Some(creds) => loader.credentials_provider(creds.clone()),
+            None => loader.no_credentials(),
+        };
+        // STS sits behind the same proxy/CA as the connection's S3 endpoint
+        let config = network::configure(loader, Some(&self.options))
+            .map_err(|e| CredentialsError::invalid_configuration(e.message))?
+            .load()
+            .await;
+
+        let mut builder = aws_sdk_sts::config::Builder::from(&config);
+        if let Some(endpoint) = sts_endpoint.filter(|e| !e.is_empty()) {
+            builder = builder.endpoint_url(endpoint);
+        }
+        Ok(aws_sdk_sts::Client::from_conf(builder.build()))
+    }
+
+    async fn assume_role(&self, base: &Credentials, role: &AssumeRoleConfig) -> Result<Credentials, CredentialsError> {
+        if role.mfa_serial.is_some() && role.mfa_token.as_deref().unwrap_or("").is_empty() {
+            return Err(CredentialsError::provider_error(format!(
+                "An MFA code is required to assume role {}",
+                role.role_arn
+            )));
+        }
+
+        let client = self.sts_client(role.sts_endpoint.as_deref(), Some(base)).await?;
+        let result = client
+            .assume_role()
+            .role_arn(&role.role_arn)
+            .role_session_name(role.session_name.as_deref().unwrap_or(DEFAULT_SESSION_NAME))
+            .set_external_id(role.external_id.clone())
+            .set_duration_seconds(role.duration_seconds)
+            .set_serial_number(role.mfa_serial.clone())
+            .set_token_code(role.mfa_token.clone())
+            .send()
+            .await
+            .map_err(|e| CredentialsError::provider_error(format!("Failed to assume role {}: {}", role.role_arn, aws_sdk_sts::error::DisplayErrorContext(e))))?;
+
+        into_credentials(result.credentials(), "BucketStack-AssumeRole")
+    }
+
+    async fn assume_role_with_web_identity(&self, web: &WebIdentityConfig) -> Result<Credentials, CredentialsError> {
+        // Re-read the file every time: tools like `aws sso` or k8s rotate it in place
+        let token = match (&web.token, &web.token_file) {
+            (Some(token), _) if !token.is_empty() => token.clone(),
+            (_, Some(path)) => std::fs::read_to_string(path)
+                .map(|t| t.trim().to_string())
+                .map_err(|e| CredentialsError::invalid_configuration(format!("Failed to read web identity token file {}: {}", path, e)))?,
+            _ => return Err(CredentialsError::invalid_configuration("Web identity requires a token or token_file")),
+        };
+
+        let client = self.sts_client(web.sts_endpoint.as_deref(), None).await?;
+        let result = client
+            .assume_role_with_web_identity()
+            .role_arn(&web.role_arn)
+            .role_session_name(web.session_name.as_deref().unwrap_or(DEFAULT_SESSION_NAME))
+            .web_identity_token(token)
+            .set_duration_seconds(web.duration_seconds)
+            .send()
+            .await
+            .map_err(|e| CredentialsError::provider_error(format!("Failed to assume role {} with web identity: {}", web.role_arn, aws_sdk_sts::error::DisplayErrorContext(e))))?;
+
+        into_credentials(result.credentials(), "BucketStack-WebIdentity")
+    }
+}
+
+fn refresh_at(creds: &Credentials, fetched_at: SystemTime) -> SystemTime {
+    match creds.expiry() {
+        Some(expiry) => expiry.checked_sub(REFRESH_MARGIN).unwrap_or(fetched_at),
+        None => fetched_at + UNEXPIRING_TTL,
+    }
+}
+
+// Reuse a cached session unless it is about to expire or, without an expiry,
+// was fetched more than `UNEXPIRING_TTL` ago. Only this key's slot stays
+// locked while fetching.
+async fn cached(
+    key: String,
+    fetch: impl Future<Output = Result<Credentials, CredentialsError>>,
+) -> Result<Credentials, CredentialsError> {
+    let slot = SESSIONS.lock().unwrap().entry(key).or_default().clone();
+    let mut session = slot.lock().await;
+    if let Some((creds, refresh_at)) = session.as_ref() {
+        if SystemTime::now() < *refresh_at {
+            return Ok(creds.clone());
+        }
+    }
+
+    let creds = fetch.await?;
+    *session = Some((creds.clone(), refresh_at(&creds, SystemTime::now())));
+    Ok(creds)
+}
+
+fn into_credentials(
+    creds: Option<&aws_sdk_sts::types::Credentials>,
+    provider_name: &'static str,
+) -> Result<Credentials, CredentialsError> {
+    let creds = creds.ok_or_else(|| CredentialsError::provider_error("STS returned no credentials"))?;
+    let expiry = SystemTime::try_from(*creds.expiration())
+        .map_err(|e| CredentialsError::unhandled(format!("Invalid session expiry: {}", e)))?;
+
+    Ok(Credentials::new(
+        creds.access_key_id(),
+        creds.secret_access_key(),
+        Some(creds.session_token().to_string()),
+        Some(expiry),
+        provider_name,
+    ))
+}
+
+impl ProvideCredentials for ConnectionCredentialsProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        future::ProvideCredentials::new(self.resolve())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_without_expiry_are_refetched() {
+        let now = SystemTime::now();
+        let profile_keys = Credentials::new("AKID", "SECRET", None, None, "profile");
+        assert_eq!(refresh_at(&profile_keys, now), now + UNEXPIRING_TTL);
+
+        let hour = Duration::from_secs(3600);
+        let session = Credentials::new("ASIA", "SECRET", Some("token".to_string()), Some(now + hour), "sts");
+        assert_eq!(refresh_at(&session, now), now + hour - REFRESH_MARGIN);
+    }
+
+    #[tokio::test]
+    async fn test_hung_fetch_only_blocks_its_own_key() {
+        let hung = tokio::spawn(cached("test-hung".to_string(), std::future::pending()));
+        tokio::task::yield_now().await;
+
+        let fetch = async { Ok(Credentials::new("AKID", "SECRET", None, None, "test")) };
+        let other = tokio::time::timeout(Duration::from_secs(5), cached("test-other".to_string(), fetch)).await;
+        assert_eq!(other.unwrap().unwrap().access_key_id(), "AKID");
+        hung.abort();
+    }
+}
@@ -0,0 +1,509 @@
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::key_rotation;
+use crate::security::{derive_passphrase_key, CredentialError, KdfParams, SecurityManager};
+
+const BUNDLE_FORMAT: &str = "bucketstack-connections";
+const BUNDLE_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+// Bundles leave the machine and can be attacked offline, so they use a
+// heavier Argon2id setting than the local vault: 64 MiB, 3 iterations
+const BUNDLE_KDF: KdfParams = KdfParams { m_cost: 64 * 1024, t_cost: 3, p_cost: 1 };
+
+// The file on disk. Everything except the ciphertext is authenticated as
+// associated data, so the KDF parameters can't be weakened by editing it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleContents {
+    exported_at: String,
+    connections: Vec<BundledConnection>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct BundledConnection {
+    // Connection metadata exactly as the frontend stores it
+    metadata: Value,
+    // Secure storage entries by suffix: bucketstack-{id}-{suffix}
+    secrets: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportSummary {
+    pub path: String,
+    pub connections: usize,
+    pub secrets: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    // Replace the existing connection (metadata and secrets), keeping its id
+    Overwrite,
+    // Import alongside the existing connection under a new id and name
+    Rename,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImportConflict {
+    pub existing_id: String,
+    pub existing_name: String,
+    // "same_id" or "same_name"
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportPreviewItem {
+    pub id: String,
+    pub name: String,
+    pub provider: Option<String>,
+    pub endpoint: Option<String>,
+    pub secret_count: usize,
+    pub conflict: Option<ImportConflict>,
+    // What importing with the same strategy would do: "added", "overwritten",
+    // "renamed" or "skipped", the id it would be saved under and, when
+    // renamed, the new name
+    pub action: String,
+    pub connection_id: Option<String>,
+    pub rename_to: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportOutcome {
+    // Id in the bundle
+    pub id: String,
+    pub name: String,
+    // "added", "overwritten", "renamed" or "skipped"
+    pub action: String,
+    // Id the connection was saved under (None when skipped)
+    pub connection_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportResult {
+    // Metadata for the frontend to upsert by id into its connection list
+    pub connections: Vec<Value>,
+    pub items: Vec<ImportOutcome>,
+}
+
+struct PlannedImport {
+    outcome: ImportOutcome,
+    conflict: Option<ImportConflict>,
+    // With the new id and name applied unless skipped
+    metadata: Value,
+    secrets: HashMap<String, String>,
+}
+
+fn field<'a>(metadata: &'a Value, name: &str) -> Option<&'a str> {
+    metadata.get(name).and_then(Value::as_str)
+}
+
+fn secret_prefix(id: &str) -> String {
+    format!("bucketstack-{}-", id)
+}
+
+fn associated_data(format: &str, version: u32, kdf: KdfParams, salt: &str) -> Vec<u8> {
+    format!("{}:{}:{}:{}:{}:{}", format, version, kdf.m_cost, kdf.t_cost, kdf.p_cost, salt).into_bytes()
+}
+
+fn seal(contents: &BundleContents, passphrase: &str, kdf: KdfParams) -> Result<Envelope, String> {
+    let mut salt_bytes = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt_bytes);
+    let mut nonce_bytes = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let salt = STANDARD.encode(salt_bytes);
+    let mut key = derive_passphrase_key(passphrase, &salt_bytes, kdf)?;
+    let mut plaintext = serde_json::to_vec(contents).map_err(|e| e.to_string())?;
+    let aad = associated_data(BUNDLE_FORMAT, BUNDLE_VERSION, kdf, &salt);
+
+    let ciphertext = Aes256Gcm::new(&key.into())
+        .encrypt(Nonce::from_slice(&nonce_bytes), Payload { msg: &plaintext, aad: &aad })
+        .map_err(|e| format!("Encryption failed: {:?}", e));
+    key.zeroize();
+    plaintext.zeroize();
+
+    Ok(Envelope {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        kdf,
+        salt,
+        nonce: STANDARD.encode(nonce_bytes),
+        ciphertext: STANDARD.encode(ciphertext?),
+    })
+}
+
+fn open(envelope: &Envelope, passphrase: &str) -> Result<BundleContents, CredentialError> {
+    if envelope.format != BUNDLE_FORMAT {
+        return Err("Not a BucketStack connection bundle".to_string().into());
+    }
+    if envelope.version != BUNDLE_VERSION {
+        return Err(format!(
+            "Unsupported bundle version {} (this build reads version {})",
+            envelope.version, BUNDLE_VERSION
+        )
+        .into());
+    }
+
+    let decode = |value: &str, what: &str| {
+        STANDARD.decode(value).map_err(|e| format!("Invalid bundle {}: {}", what, e))
+    };
+    let salt = decode(&envelope.salt, "salt")?;
+    let nonce = decode(&envelope.nonce, "nonce")?;
+    let ciphertext = decode(&envelope.ciphertext, "ciphertext")?;
+    if nonce.len() != 12 {
+        return Err("Invalid bundle nonce".to_string().into());
+    }
+    // Bounded, so a crafted bundle can't tie up memory or CPU before the passphrase is checked
+    let mut key = derive_passphrase_key(passphrase, &salt, envelope.kdf).map_err(|e| format!("Invalid bundle: {}", e))?;
+    let aad = associated_data(&envelope.format, envelope.version, envelope.kdf, &envelope.salt);
+    let plaintext = Aes256Gcm::new(&key.into())
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad })
+        .map_err(|_| CredentialError::IncorrectPassphrase);
+    key.zeroize();
+
+    let mut plaintext = plaintext?;
+    let contents = serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid bundle contents: {}", e));
+    plaintext.zeroize();
+    Ok(contents?)
+}
+
+fn read_bundle(path: &Path, passphrase: &str) -> Result<BundleContents, CredentialError> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read bundle: {}", e))?;
+    let envelope: Envelope =
+        serde_json::from_str(&json).map_err(|_| "Not a BucketStack connection bundle".to_string())?;
+    open(&envelope, passphrase)
+}
+
+/// Write the selected connections (all when `ids` is None) and their secrets
+/// to a passphrase-encrypted bundle that can be imported on another machine
+pub fn export_connections(
+    manager: &mut dyn SecurityManager,
+    path: &Path,
+    passphrase: &str,
+    connections: &[Value],
+    ids: Option<&[String]>,
+) -> Result<ExportSummary, CredentialError> {
+    if passphrase.chars().count() < 8 {
+        return Err("Passphrase must be at least 8 characters".to_string().into());
+    }
+
+    let keys = manager.keys()?;
+    let mut bundled = Vec::new();
+    let mut secret_count = 0;
+
+    for metadata in connections {
+        let id = field(metadata, "id").ok_or_else(|| "Connection is missing an id".to_string())?;
+        if ids.is_some_and(|ids| !ids.iter().any(|i| i == id)) {
+            continue;
+        }
+
+        let prefix = secret_prefix(id);
+        let mut secrets = HashMap::new();
+        for key in keys.iter().filter(|k| k.starts_with(&prefix)) {
+            if let Some(value) = manager.get_item(key)? {
+                secrets.insert(key[prefix.len()..].to_string(), value);
+            }
+        }
+        secret_count += secrets.len();
+        bundled.push(BundledConnection { metadata: metadata.clone(), secrets });
+    }
+
+    if bundled.is_empty() {
+        return Err("No connections selected for export".to_string().into());
+    }
+
+    let contents = BundleContents {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        connections: bundled,
+    };
+    let envelope = seal(&contents, passphrase, BUNDLE_KDF)?;
+    let json = serde_json::to_string_pretty(&envelope).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| format!("Failed to write bundle: {}", e))?;
+
+    Ok(ExportSummary {
+        path: path.to_string_lossy().to_string(),
+        connections: contents.connections.len(),
+        secrets: secret_count,
+    })
+}
+
+// Match by id first (re-importing our own export), then by display name
+fn find_conflict(metadata: &Value, existing: &[Value]) -> Option<ImportConflict> {
+    let conflict = |other: &Value, reason: &str| ImportConflict {
+        existing_id: field(other, "id").unwrap_or_default().to_string(),
+        existing_name: field(other, "name").unwrap_or_default().to_string(),
+        reason: reason.to_string(),
+    };
+
+    let id = field(metadata, "id");
+    if let Some(other) = existing.iter().find(|e| id.is_some() && field(e, "id") == id) {
+        return Some(conflict(other, "same_id"));
+    }
+    let name = field(metadata, "name")?.to_lowercase();
+    existing
+        .iter()
+        .find(|e| field(e, "name").map(str::to_lowercase).as_deref() == Some(name.as_str()))
+        .map(|other| conflict(other, "same_name"))
+}
+
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    let mut candidate = format!("{} (imported)", name);
+    let mut n = 2;
+    while taken.contains(&candidate.to_lowercase()) {
+        candidate = format!("{} (imported {})", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+// Same shape as the frontend's crypto.randomUUID()
+// Id for a renamed copy, derived from the bundled id and the ids already in
+// use so the preview and the import it confirms come up with the same one
+fn renamed_connection_id(bundled_id: &str, existing_ids: &[&str], used: &HashSet<String>) -> String {
+    (0u32..)
+        .map(|attempt| {
+            let mut hasher = Sha256::new();
+            for id in existing_ids {
+                hasher.update(id.as_bytes());
+                hasher.update([0]);
+            }
+            hasher.update(bundled_id.as_bytes());
+            hasher.update(attempt.to_le_bytes());
+            let digest = hasher.finalize();
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&digest[..16]);
+            uuid_v4_format(bytes)
+        })
+        .find(|id| !used.contains(id))
+        .expect("some attempt yields an unused id")
+}
+
+fn uuid_v4_format(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+fn plan_import(
+    bundle: Vec<BundledConnection>,
+    existing: &[Value],
+    strategy: ConflictStrategy,
+    overrides: &HashMap<String, ConflictStrategy>,
+    ids: Option<&[String]>,
+) -> Vec<PlannedImport> {
+    let mut taken: HashSet<String> = existing
+        .iter()
+        .filter_map(|e| field(e, "name"))
+        .map(str::to_lowercase)
+        .collect();
+    let mut existing_ids: Vec<&str> = existing.iter().filter_map(|e| field(e, "id")).collect();
+    existing_ids.sort_unstable();
+    let mut used: HashSet<String> = existing_ids.iter().map(|id| id.to_string()).collect();
+    let mut plans = Vec::new();
+
+    for BundledConnection { mut metadata, secrets } in bundle {
+        if !metadata.is_object() {
+            continue;
+        }
+        let id = field(&metadata, "id").unwrap_or_default().to_string();
+        let name = field(&metadata, "name").unwrap_or_default().to_string();
+        if ids.is_some_and(|ids| !ids.contains(&id)) {
+            continue;
+        }
+
+        let conflict = find_conflict(&metadata, existing);
+        let strategy = overrides.get(&id).copied().unwrap_or(strategy);
+        let (action, target_id) = match (&conflict, strategy) {
+            (None, _) => ("added", id.clone()),
+            (Some(_), ConflictStrategy::Skip) => ("skipped", String::new()),
+            (Some(c), ConflictStrategy::Overwrite) => ("overwritten", c.existing_id.clone()),
+            (Some(_), ConflictStrategy::Rename) => {
+                let renamed = unique_name(&name, &taken);
+                metadata["name"] = Value::String(renamed);
+                ("renamed", renamed_connection_id(&id, &existing_ids, &used))
+            }
+        };
+
+        let skipped = action == "skipped";
+        if !skipped {
+            metadata["id"] = Value::String(target_id.clone());
+            if let Some(name) = field(&metadata, "name") {
+                taken.insert(name.to_lowercase());
+            }
+            used.insert(target_id.clone());
+        }
+
+        plans.push(PlannedImport {
+            outcome: ImportOutcome {
+                id,
+                name,
+                action: action.to_string(),
+                connection_id: (!skipped).then_some(target_id),
+            },
+            conflict,
+            metadata,
+            secrets,
+        });
+    }
+    plans
+}
+
+/// Decrypt a bundle and report what `import_connections` with the same
+/// arguments would do, without changing anything
+pub fn preview_import(
+    path: &Path,
+    passphrase: &str,
+    existing: &[Value],
+    strategy: ConflictStrategy,
+    overrides: &HashMap<String, ConflictStrategy>,
+    ids: Option<&[String]>,
+) -> Result<Vec<ImportPreviewItem>, CredentialError> {
+    let contents = read_bundle(path, passphrase)?;
+    Ok(preview_plans(plan_import(contents.connections, existing, strategy, overrides, ids)))
+}
+
+fn preview_plans(plans: Vec<PlannedImport>) -> Vec<ImportPreviewItem> {
+    plans
+        .into_iter()
+        .map(|plan| ImportPreviewItem {
+            provider: field(&plan.metadata, "provider").map(str::to_string),
+            endpoint: field(&plan.metadata, "endpoint").map(str::to_string),
+            secret_count: plan.secrets.len(),
+            conflict: plan.conflict,
+            rename_to: (plan.outcome.action == "renamed")
+                .then(|| field(&plan.metadata, "name").unwrap_or_default().to_string()),
+            action: plan.outcome.action,
+            connection_id: plan.outcome.connection_id,
+            id: plan.outcome.id,
+            name: plan.outcome.name,
+        })
+        .collect()
+}
+
+/// Merge a bundle into secure storage. `existing` is the frontend's current
+/// connection list; conflicts use `strategy` unless `overrides` names a
+/// different one for that bundle id.
+pub fn import_connections(
+    manager: &mut dyn SecurityManager,
+    path: &Path,
+    passphrase: &str,
+    existing: &[Value],
+    strategy: ConflictStrategy,
+    overrides: &HashMap<String, ConflictStrategy>,
+    ids: Option<&[String]>,
+) -> Result<ImportResult, CredentialError> {
+    let contents = read_bundle(path, passphrase)?;
+    let plans = plan_import(contents.connections, existing, strategy, overrides, ids);
+    let keys = manager.keys()?;
+
+    let mut result = ImportResult { connections: Vec::new(), items: Vec::new() };
+    for plan in plans {
+        if let Some(id) = plan.outcome.connection_id.as_deref() {
+            let prefix = secret_prefix(id);
+            // Drop secrets the replaced connection had that the bundle doesn't
+            if plan.outcome.action == "overwritten" {
+                for key in keys.iter().filter(|k| k.starts_with(&prefix)) {
+                    if !plan.secrets.contains_key(&key[prefix.len()..]) {
+                        key_rotation::delete_secret(manager, key)?;
+                    }
+                }
+            }
+            for (suffix, value) in plan.secrets {
+                key_rotation::save_secret(manager, format!("{}{}", prefix, suffix), value)?;
+            }
+            result.connections.push(plan.metadata);
+        }
+        result.items.push(plan.outcome);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bundled(id: &str, name: &str) -> BundledConnection {
+        BundledConnection {
+            metadata: json!({ "id": id, "name": name, "provider": "aws" }),
+            secrets: HashMap::from([("access".to_string(), format!("AKID-{}", id))]),
+        }
+    }
+
+    #[test]
+    fn test_bundle_round_trip_and_wrong_passphrase() {
+        let contents = BundleContents {
+            exported_at: "2026-01-01T00:00:00Z".to_string(),
+            connections: vec![bundled("a", "Prod")],
+        };
+        // Light parameters keep the test fast; the format records them either way
+        let kdf = KdfParams { m_cost: 1024, t_cost: 1, p_cost: 1 };
+        let envelope = seal(&contents, "team passphrase", kdf).unwrap();
+
+        let opened = open(&envelope, "team passphrase").unwrap();
+        assert_eq!(opened.connections[0].secrets["access"], "AKID-a");
+        assert_eq!(open(&envelope, "wrong passphrase").unwrap_err(), CredentialError::IncorrectPassphrase);
+
+        // Crafted parameters are refused before Argon2 runs
+        for crafted in [KdfParams { t_cost: u32::MAX, ..kdf }, KdfParams { p_cost: u32::MAX, ..kdf }] {
+            let mut envelope: Envelope = serde_json::from_value(serde_json::to_value(&envelope).unwrap()).unwrap();
+            envelope.kdf = crafted;
+            assert!(matches!(open(&envelope, "team passphrase"), Err(CredentialError::Storage { message }) if message.contains("out of range")));
+        }
+
+        // The KDF parameters are authenticated, so they can't be weakened
+        let weakened = Envelope { kdf: KdfParams { m_cost: 512, ..kdf }, ..envelope };
+        assert!(open(&weakened, "team passphrase").is_err());
+    }
+
+    #[test]
+    fn test_plan_import_conflict_strategies() {
+        let existing = vec![json!({ "id": "a", "name": "Prod" }), json!({ "id": "x", "name": "Backups" })];
+        let bundle = vec![bundled("a", "Prod"), bundled("b", "backups"), bundled("c", "Staging")];
+        let overrides = HashMap::from([("b".to_string(), ConflictStrategy::Overwrite)]);
+
+        let plans = plan_import(bundle.clone(), &existing, ConflictStrategy::Skip, &overrides, None);
+        let actions: Vec<_> = plans.iter().map(|p| p.outcome.action.as_str()).collect();
+        assert_eq!(actions, ["skipped", "overwritten", "added"]);
+        // A same-name overwrite keeps the existing connection's id
+        assert_eq!(plans[1].outcome.connection_id.as_deref(), Some("x"));
+
+        let plans = plan_import(bundle.clone(), &existing, ConflictStrategy::Rename, &HashMap::new(), Some(&["a".to_string()]));
+        assert_eq!(plans.len(), 1);
+        let metadata = &plans[0].metadata;
+        assert_eq!(metadata["name"], "Prod (imported)");
+        assert_ne!(metadata["id"], "a");
+
+        // The preview reports the same plan the import would carry out, new id included
+        let plans = plan_import(bundle.clone(), &existing, ConflictStrategy::Rename, &overrides, None);
+        let preview = preview_plans(plan_import(bundle, &existing, ConflictStrategy::Rename, &overrides, None));
+        assert_eq!(preview[0].connection_id, plans[0].outcome.connection_id);
+        let actions: Vec<_> = preview.iter().map(|p| (p.action.as_str(), p.rename_to.as_deref())).collect();
+        assert_eq!(actions, [("renamed", Some("Prod (imported)")), ("overwritten", None), ("added", None)]);
+        assert_eq!(preview[1].connection_id.as_deref(), Some("x"));
+        assert_eq!(preview[0].conflict.as_ref().map(|c| c.reason.as_str()), Some("same_id"));
+    }
+}
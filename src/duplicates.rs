@@ -0,0 +1,301 @@
+use aws_sdk_s3::Client as S3Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::connection::ConnectionOptions;
+use crate::error::BucketStackError;
+
+/// One place to look for duplicates. Sources may use different connections.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateSource {
+    pub endpoint: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub connection: Option<ConnectionOptions>,
+}
+
+/// Which copy of a group survives a cleanup
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepPolicy {
+    #[default]
+    Oldest,
+    Newest,
+    ShortestKey,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DuplicateOptions {
+    // Download and hash objects whose multipart ETags can't be compared
+    #[serde(default)]
+    pub hash_multipart: bool,
+    #[serde(default)]
+    pub keep: KeepPolicy,
+    // Smaller objects are ignored; defaults to 1 so empty files never match
+    pub min_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DuplicateObject {
+    // Index into the sources the scan was given
+    pub source: usize,
+    pub bucket: String,
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub last_modified: String,
+    pub storage_class: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMethod {
+    Etag,
+    ContentHash,
+}
+
+/// Objects with the same size and fingerprint. `objects[keep]` is the copy
+/// a cleanup leaves in place.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub fingerprint: String,
+    pub method: MatchMethod,
+    pub objects: Vec<DuplicateObject>,
+    pub keep: usize,
+}
+
+impl DuplicateGroup {
+    pub fn redundant(&self) -> impl Iterator<Item = &DuplicateObject> {
+        self.objects.iter().enumerate().filter(move |(i, _)| *i != self.keep).map(|(_, o)| o)
+    }
+
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.objects.len() as u64 - 1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DuplicateReport {
+    pub scanned: u64,
+    pub hashed: u64,
+    pub groups: Vec<DuplicateGroup>,
+    pub duplicate_objects: u64,
+    pub wasted_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkippedDeletion {
+    pub object: DuplicateObject,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DeletionReport {
+    pub dry_run: bool,
+    // With `dry_run`, what would have been deleted
+    pub deleted: Vec<DuplicateObject>,
+    pub skipped: Vec<SkippedDeletion>,
+    pub freed_bytes: u64,
+}
+
+pub fn normalize_etag(etag: &str) -> String {
+    etag.trim_matches('"').to_lowercase()
+}
+
+// Multipart ETags ("<md5 of part md5s>-<parts>") depend on the part size the
+// uploader chose, so equal content can carry different ETags
+pub fn is_multipart_etag(etag: &str) -> bool {
+    etag.contains('-')
+}
+
+/// Where an object physically lives. Bucket names are global on AWS, so
+/// every AWS endpoint (including the empty default) is the same place.
+pub fn object_identity(endpoint: &str, bucket: &str, key: &str) -> (String, String, String) {
+    let endpoint = if crate::endpoint::is_aws_endpoint(endpoint) {
+        "aws".to_string()
+    } else {
+        endpoint.trim().trim_end_matches('/').to_lowercase()
+    };
+    (endpoint, bucket.to_string(), key.to_string())
+}
+
+/// Scan results with each physical object once, however many overlapping
+/// sources (nested prefixes, repeated sources, other connections) listed it
+#[derive(Default)]
+pub struct Candidates {
+    seen: HashSet<(String, String, String)>,
+    pub objects: Vec<DuplicateObject>,
+}
+
+impl Candidates {
+    pub fn add(&mut self, endpoint: &str, object: DuplicateObject) {
+        if self.seen.insert(object_identity(endpoint, &object.bucket, &object.key)) {
+            self.objects.push(object);
+        }
+    }
+}
+
+/// Candidates that share a size with at least one other candidate, by size
+pub fn by_size(objects: Vec<DuplicateObject>) -> BTreeMap<u64, Vec<DuplicateObject>> {
+    let mut sizes: BTreeMap<u64, Vec<DuplicateObject>> = BTreeMap::new();
+    for object in objects {
+        sizes.entry(object.size).or_default().push(object);
+    }
+    sizes.retain(|_, group| group.len() > 1);
+    sizes
+}
+
+/// A same-size group only needs hashing when ETags can't settle it: some are
+/// multipart and they don't all agree
+pub fn needs_content_hash(group: &[DuplicateObject]) -> bool {
+    let etags: HashSet<String> = group.iter().filter_map(|o| o.etag.as_deref()).map(normalize_etag).collect();
+    etags.len() > 1 && etags.iter().any(|e| is_multipart_etag(e))
+}
+
+fn keep_index(objects: &[DuplicateObject], policy: KeepPolicy) -> usize {
+    let best = match policy {
+        KeepPolicy::Oldest => objects.iter().enumerate().min_by(|(_, a), (_, b)| a.last_modified.cmp(&b.last_modified)),
+        KeepPolicy::Newest => objects.iter().enumerate().max_by(|(_, a), (_, b)| a.last_modified.cmp(&b.last_modified)),
+        KeepPolicy::ShortestKey => objects.iter().enumerate().min_by_key(|(_, o)| (o.key.len(), o.key.clone())),
+    };
+    best.map(|(i, _)| i).unwrap_or(0)
+}
+
+/// Group fingerprinted objects of one size, keeping groups with copies
+pub fn group(size: u64, fingerprinted: Vec<(DuplicateObject, String, MatchMethod)>, policy: KeepPolicy) -> Vec<DuplicateGroup> {
+    let mut by_fingerprint: HashMap<String, (MatchMethod, Vec<DuplicateObject>)> = HashMap::new();
+    for (object, fingerprint, method) in fingerprinted {
+        by_fingerprint.entry(fingerprint).or_insert((method, Vec::new())).1.push(object);
+    }
+    let mut groups: Vec<DuplicateGroup> = by_fingerprint
+        .into_iter()
+        .filter(|(_, (_, objects))| objects.len() > 1)
+        .map(|(fingerprint, (method, mut objects))| {
+            objects.sort_by(|a, b| (a.source, &a.key).cmp(&(b.source, &b.key)));
+            DuplicateGroup { size, keep: keep_index(&objects, policy), fingerprint, method, objects }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.fingerprint.cmp(&b.fingerprint));
+    groups
+}
+
+pub fn report(scanned: u64, hashed: u64, mut groups: Vec<DuplicateGroup>) -> DuplicateReport {
+    // Biggest savings first
+    groups.sort_by_key(|g| std::cmp::Reverse(g.wasted_bytes()));
+    DuplicateReport {
+        scanned,
+        hashed,
+        duplicate_objects: groups.iter().map(|g| g.objects.len() as u64 - 1).sum(),
+        wasted_bytes: groups.iter().map(|g| g.wasted_bytes()).sum(),
+        groups,
+    }
+}
+
+/// SHA-256 of an object's content, read as a stream
+pub async fn content_hash(client: &S3Client, bucket: &str, key: &str) -> Result<String, BucketStackError> {
+    let mut body = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| BucketStackError::wrap(format!("Failed to read {}", key), e))?
+        .body;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = body
+        .try_next()
+        .await
+        .map_err(|e| BucketStackError::wrap(format!("Failed to read {}", key), e))?
+    {
+        hasher.update(&chunk);
+    }
+    Ok(format!("sha256:{}", hex::encode(hasher.finalize())))
+}
+
+/// Check an object is still what the scan saw before acting on it
+pub async fn verify(client: &S3Client, object: &DuplicateObject) -> Result<(), String> {
+    let head = client
+        .head_object()
+        .bucket(&object.bucket)
+        .key(&object.key)
+        .send()
+        .await
+        .map_err(|e| BucketStackError::wrap("Object is unavailable", e).message)?;
+    let etag = head.e_tag().map(normalize_etag);
+    let same_etag = object.etag.is_none() || etag == object.etag.as_deref().map(normalize_etag);
+    if head.content_length().unwrap_or(0).max(0) as u64 != object.size || !same_etag {
+        return Err("Changed since the scan".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(source: usize, key: &str, size: u64, etag: &str, last_modified: &str) -> DuplicateObject {
+        DuplicateObject {
+            source,
+            bucket: "b".to_string(),
+            key: key.to_string(),
+            size,
+            etag: Some(etag.to_string()),
+            last_modified: last_modified.to_string(),
+            storage_class: None,
+        }
+    }
+
+    #[test]
+    fn test_overlapping_sources_list_an_object_once() {
+        // Same bucket scanned with prefixes "" and "photos/", once through a
+        // regional AWS endpoint
+        let mut candidates = Candidates::default();
+        candidates.add("", object(0, "photos/cat.jpg", 100, "\"abc\"", ""));
+        candidates.add("", object(0, "photos/copy.jpg", 100, "\"abc\"", ""));
+        candidates.add("https://s3.eu-west-1.amazonaws.com", object(1, "photos/cat.jpg", 100, "\"abc\"", ""));
+        candidates.add("http://minio.test:9000", object(2, "photos/cat.jpg", 100, "\"abc\"", ""));
+        candidates.add("http://MINIO.test:9000/", object(3, "photos/cat.jpg", 100, "\"abc\"", ""));
+        let keys: Vec<_> = candidates.objects.iter().map(|o| (o.source, o.key.as_str())).collect();
+        assert_eq!(keys, [(0, "photos/cat.jpg"), (0, "photos/copy.jpg"), (2, "photos/cat.jpg")]);
+
+        assert_eq!(
+            object_identity("", "b", "photos/cat.jpg"),
+            object_identity("https://s3.amazonaws.com", "b", "photos/cat.jpg")
+        );
+    }
+
+    #[test]
+    fn test_groups_by_size_and_etag_and_keeps_one() {
+        let sizes = by_size(vec![
+            object(0, "a/report.pdf", 100, "\"abc\"", "2024-01-02T00:00:00Z"),
+            object(1, "report (1).pdf", 100, "\"ABC\"", "2024-01-01T00:00:00Z"),
+            object(0, "other.pdf", 100, "\"def\"", "2024-01-03T00:00:00Z"),
+            object(0, "unique.bin", 7, "\"abc\"", "2024-01-03T00:00:00Z"),
+        ]);
+        assert_eq!(sizes.keys().collect::<Vec<_>>(), [&100]);
+        assert!(!needs_content_hash(&sizes[&100]));
+
+        let fingerprinted = sizes[&100]
+            .iter()
+            .map(|o| (o.clone(), normalize_etag(o.etag.as_deref().unwrap()), MatchMethod::Etag))
+            .collect();
+        let groups = group(100, fingerprinted, KeepPolicy::Oldest);
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!(group.objects[group.keep].key, "report (1).pdf");
+        assert_eq!(group.redundant().map(|o| o.key.as_str()).collect::<Vec<_>>(), ["a/report.pdf"]);
+
+        let report = report(4, 0, groups);
+        assert_eq!((report.duplicate_objects, report.wasted_bytes), (1, 100));
+
+        let mixed = [object(0, "x", 9, "\"abc\"", ""), object(0, "y", 9, "\"def-2\"", "")];
+        assert!(needs_content_hash(&mixed));
+    }
+}
@@ -0,0 +1,253 @@
+use aws_sdk_s3::config::Builder as S3ConfigBuilder;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressingStyle {
+    // Virtual-hosted on AWS and providers known to need it, path-style elsewhere
+    #[default]
+    Auto,
+    // https://endpoint/bucket/key
+    Path,
+    // https://bucket.endpoint/key
+    Virtual,
+}
+
+/// What an AWS S3 endpoint URL asks for. The SDK builds the actual host from
+/// these, so recognised AWS endpoints are never passed as a custom URL.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AwsEndpoint {
+    pub region: Option<String>,
+    pub fips: bool,
+    pub dualstack: bool,
+    pub accelerate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProviderPreset {
+    // Matches the frontend's provider ids
+    pub provider: String,
+    pub label: String,
+    // <ACCOUNT_ID> and <REGION> are placeholders for the user to fill in
+    pub endpoint: String,
+    pub default_region: String,
+    pub addressing_style: AddressingStyle,
+    // Endpoint hosts end with this; used to pick a style in auto mode
+    #[serde(skip)]
+    host_suffix: Option<&'static str>,
+}
+
+fn preset(
+    provider: &str,
+    label: &str,
+    endpoint: &str,
+    default_region: &str,
+    addressing_style: AddressingStyle,
+    host_suffix: Option<&'static str>,
+) -> ProviderPreset {
+    ProviderPreset {
+        provider: provider.to_string(),
+        label: label.to_string(),
+        endpoint: endpoint.to_string(),
+        default_region: default_region.to_string(),
+        addressing_style,
+        host_suffix,
+    }
+}
+
+pub fn presets() -> Vec<ProviderPreset> {
+    use AddressingStyle::{Path, Virtual};
+    vec![
+        preset("aws", "Amazon S3", "", "us-east-1", Virtual, None),
+        // R2 supports both, but virtual-hosted needs the bucket in the cert
+        preset("cloudflare", "Cloudflare R2", "https://<ACCOUNT_ID>.r2.cloudflarestorage.com", "auto", Path, Some(".r2.cloudflarestorage.com")),
+        preset("backblaze", "Backblaze B2", "https://s3.<REGION>.backblazeb2.com", "us-west-004", Virtual, Some(".backblazeb2.com")),
+        preset("wasabi", "Wasabi", "https://s3.<REGION>.wasabisys.com", "us-east-1", Path, Some(".wasabisys.com")),
+        preset("digitalocean", "DigitalOcean Spaces", "https://<REGION>.digitaloceanspaces.com", "nyc3", Virtual, Some(".digitaloceanspaces.com")),
+        preset("railway", "Railway", "https://storage.railway.app", "auto", Virtual, Some("storage.railway.app")),
+        preset("minio", "MinIO", "http://localhost:9000", "us-east-1", Path, None),
+        preset("custom", "Custom", "", "us-east-1", Path, None),
+    ]
+}
+
+fn host(endpoint: &str) -> String {
+    let endpoint = endpoint.trim();
+    let without_scheme = endpoint.split_once("://").map_or(endpoint, |(_, rest)| rest);
+    let authority = without_scheme.split(['/', '?']).next().unwrap_or("");
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    host.split(':').next().unwrap_or("").to_ascii_lowercase()
+}
+
+/// Recognise AWS S3 API endpoints: global, regional (s3.<region> and the
+/// legacy s3-<region>), FIPS, dual-stack, Transfer Acceleration and China.
+/// An empty endpoint means AWS with the connection's region.
+pub fn parse_aws_endpoint(endpoint: &str) -> Option<AwsEndpoint> {
+    if endpoint.trim().is_empty() {
+        return Some(AwsEndpoint::default());
+    }
+    let host = host(endpoint);
+    let rest = host
+        .strip_suffix(".amazonaws.com")
+        .or_else(|| host.strip_suffix(".amazonaws.com.cn"))?;
+
+    let mut labels = rest.split('.');
+    let mut parsed = AwsEndpoint::default();
+    match labels.next()? {
+        "s3" => {}
+        "s3-fips" => parsed.fips = true,
+        "s3-accelerate" => parsed.accelerate = true,
+        "s3-external-1" => parsed.region = Some("us-east-1".to_string()),
+        // Website, control and access point hosts aren't the S3 API
+        label if label.starts_with("s3-website") || label.starts_with("s3-control") => return None,
+        label => parsed.region = Some(label.strip_prefix("s3-")?.to_string()),
+    }
+    for label in labels {
+        match label {
+            "dualstack" if !parsed.dualstack => parsed.dualstack = true,
+            // VPC endpoints (vpce) and bucket hosts need the URL as given
+            region if parsed.region.is_none() && !parsed.accelerate && is_region(region) => {
+                parsed.region = Some(region.to_string())
+            }
+            _ => return None,
+        }
+    }
+    Some(parsed)
+}
+
+fn is_region(label: &str) -> bool {
+    label.contains('-') && label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+pub fn is_aws_endpoint(endpoint: &str) -> bool {
+    parse_aws_endpoint(endpoint).is_some()
+}
+
+/// Preset id for the provider behind `endpoint`; "custom" when unrecognised
+pub fn provider_for(endpoint: &str) -> String {
+    if is_aws_endpoint(endpoint) {
+        return "aws".to_string();
+    }
+    let host = host(endpoint);
+    presets()
+        .into_iter()
+        .find(|p| p.host_suffix.is_some_and(|suffix| host.ends_with(suffix)))
+        .map_or_else(|| "custom".to_string(), |p| p.provider)
+}
+
+/// Style actually used for `endpoint` once `auto` is resolved
+pub fn resolve_style(endpoint: &str, style: AddressingStyle) -> AddressingStyle {
+    if style != AddressingStyle::Auto {
+        return style;
+    }
+    if is_aws_endpoint(endpoint) {
+        return AddressingStyle::Virtual;
+    }
+    let host = host(endpoint);
+    presets()
+        .into_iter()
+        .find(|p| p.host_suffix.is_some_and(|suffix| host.ends_with(suffix)))
+        .map_or(AddressingStyle::Path, |p| p.addressing_style)
+}
+
+/// Point an S3 config at the connection's endpoint. Returns the region the
+/// endpoint names, if any, which must win over the connection's region for
+/// signing to work.
+pub fn configure(builder: S3ConfigBuilder, endpoint: &str, style: AddressingStyle) -> (S3ConfigBuilder, Option<String>) {
+    let path_style = resolve_style(endpoint, style) == AddressingStyle::Path;
+    match parse_aws_endpoint(endpoint) {
+        Some(aws) => {
+            let builder = builder
+                .use_fips(aws.fips)
+                .use_dual_stack(aws.dualstack)
+                .accelerate(aws.accelerate)
+                .force_path_style(path_style);
+            (builder, aws.region)
+        }
+        None => (builder.endpoint_url(endpoint.trim()).force_path_style(path_style), None),
+    }
+}
+
+/// Base URL of a bucket, for requests we build ourselves (browser POST forms).
+/// `region` is the one requests are signed for, already resolved from the
+/// bucket's location and the endpoint, so on AWS it also picks the host.
+pub fn bucket_url(endpoint: &str, region: &str, bucket: &str, style: AddressingStyle) -> String {
+    let path_style = resolve_style(endpoint, style) == AddressingStyle::Path;
+    let (scheme, host) = match parse_aws_endpoint(endpoint) {
+        Some(aws) => {
+            let domain = if region.starts_with("cn-") { "amazonaws.com.cn" } else { "amazonaws.com" };
+            let dualstack = if aws.dualstack { ".dualstack" } else { "" };
+            let host = if aws.accelerate {
+                format!("s3-accelerate{}.{}", dualstack, domain)
+            } else {
+                let service = if aws.fips { "s3-fips" } else { "s3" };
+                format!("{}{}.{}.{}", service, dualstack, region, domain)
+            };
+            ("https".to_string(), host)
+        }
+        None => {
+            let endpoint = endpoint.trim().trim_end_matches('/');
+            match endpoint.split_once("://") {
+                Some((scheme, host)) => (scheme.to_string(), host.to_string()),
+                None => ("https".to_string(), endpoint.to_string()),
+            }
+        }
+    };
+
+    if path_style {
+        format!("{}://{}/{}", scheme, host, bucket)
+    } else {
+        format!("{}://{}.{}/", scheme, bucket, host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aws_endpoints() {
+        let parse = |e: &str| parse_aws_endpoint(e);
+        assert_eq!(parse("https://s3.amazonaws.com"), Some(AwsEndpoint::default()));
+        assert_eq!(parse("https://s3.eu-west-1.amazonaws.com").unwrap().region.as_deref(), Some("eu-west-1"));
+        assert_eq!(parse("https://s3-eu-west-1.amazonaws.com/").unwrap().region.as_deref(), Some("eu-west-1"));
+        assert_eq!(
+            parse("https://s3-fips.dualstack.us-gov-west-1.amazonaws.com"),
+            Some(AwsEndpoint { region: Some("us-gov-west-1".to_string()), fips: true, dualstack: true, accelerate: false })
+        );
+        assert_eq!(
+            parse("https://s3-accelerate.dualstack.amazonaws.com"),
+            Some(AwsEndpoint { region: None, fips: false, dualstack: true, accelerate: true })
+        );
+        assert_eq!(parse("https://s3.cn-north-1.amazonaws.com.cn").unwrap().region.as_deref(), Some("cn-north-1"));
+
+        assert_eq!(parse("https://bucket.vpce-0a1b2c3d.s3.us-east-1.vpce.amazonaws.com"), None);
+        assert_eq!(parse("http://s3-website-us-east-1.amazonaws.com"), None);
+        assert_eq!(parse("https://nyc3.digitaloceanspaces.com"), None);
+    }
+
+    #[test]
+    fn test_addressing_style_and_bucket_urls() {
+        use AddressingStyle::*;
+        assert_eq!(resolve_style("https://s3.eu-west-1.amazonaws.com", Auto), Virtual);
+        assert_eq!(resolve_style("https://fra1.digitaloceanspaces.com", Auto), Virtual);
+        assert_eq!(resolve_style("https://abc.r2.cloudflarestorage.com", Auto), Path);
+        assert_eq!(resolve_style("http://minio.lan:9000", Auto), Path);
+        assert_eq!(resolve_style("http://minio.lan:9000", Virtual), Virtual);
+
+        assert_eq!(bucket_url("", "eu-west-1", "uploads", Auto), "https://uploads.s3.eu-west-1.amazonaws.com/");
+        assert_eq!(
+            bucket_url("https://s3.dualstack.us-east-2.amazonaws.com", "us-east-2", "b", Auto),
+            "https://b.s3.dualstack.us-east-2.amazonaws.com/"
+        );
+        // A bucket found in another region than the endpoint names
+        assert_eq!(
+            bucket_url("https://s3.us-east-1.amazonaws.com", "eu-west-1", "b", Auto),
+            "https://b.s3.eu-west-1.amazonaws.com/"
+        );
+        assert_eq!(bucket_url("https://minio.local:9000/", "us-east-1", "b", Auto), "https://minio.local:9000/b");
+        assert_eq!(
+            bucket_url("https://fra1.digitaloceanspaces.com", "fra1", "b", Auto),
+            "https://b.fra1.digitaloceanspaces.com/"
+        );
+    }
+}
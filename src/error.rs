@@ -0,0 +1,352 @@
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_types::request_id::RequestId;
+use serde::{Deserialize, Serialize};
+
+use crate::security::CredentialError;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    AccessDenied,
+    // Unknown key id, bad signature, expired session token
+    InvalidCredentials,
+    NotFound,
+    AlreadyExists,
+    // Bucket not empty, precondition failed, concurrent operation, archived
+    // (Glacier / Deep Archive) object that has to be restored first
+    Conflict,
+    InvalidRequest,
+    // The provider doesn't implement the API (R2 ACLs, B2 object tagging)
+    Unsupported,
+    Throttled,
+    ServiceUnavailable,
+    Timeout,
+    Network,
+    VaultLocked,
+    IncorrectPassphrase,
+    CredentialsQuarantined,
+    // Credentials file from a newer release
+    UnsupportedCredentialFormat,
+    // Local file system
+    Io,
+    Cancelled,
+    Internal,
+}
+
+/// Error returned by every command. `code`, `http_status` and `request_id`
+/// come from the provider when the failure was a service response.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BucketStackError {
+    pub kind: ErrorKind,
+    // Provider error code, e.g. "NoSuchBucket" or "SlowDown"
+    pub code: Option<String>,
+    pub http_status: Option<u16>,
+    pub request_id: Option<String>,
+    // Trying the same request again may succeed
+    pub retryable: bool,
+    pub message: String,
+}
+
+impl BucketStackError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            code: None,
+            http_status: None,
+            request_id: None,
+            retryable: matches!(
+                kind,
+                ErrorKind::Throttled | ErrorKind::ServiceUnavailable | ErrorKind::Timeout | ErrorKind::Network
+            ),
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidRequest, message)
+    }
+
+    /// Classify `err` and prefix its message with what we were doing,
+    /// e.g. `wrap("Failed to list objects", e)`
+    pub fn wrap(context: impl AsRef<str>, err: impl Classify) -> Self {
+        let mut error = err.classify();
+        error.message = format!("{}: {}", context.as_ref(), error.message);
+        error
+    }
+
+    /// Read back an activity log `error_message`; rows written before errors
+    /// were structured hold plain text
+    pub fn from_stored(text: &str) -> Self {
+        serde_json::from_str(text).unwrap_or_else(|_| text.to_string().into())
+    }
+
+    /// Errors sent by the frontend, either structured or as plain text
+    pub fn from_value(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::String(text) => Self::from_stored(&text),
+            other => serde_json::from_value(other.clone()).unwrap_or_else(|_| other.to_string().into()),
+        }
+    }
+
+    pub fn to_stored(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| self.message.clone())
+    }
+}
+
+impl std::fmt::Display for BucketStackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for BucketStackError {}
+
+// Error codes shared by S3 and the S3-compatible providers we support
+fn kind_for_code(code: &str) -> Option<ErrorKind> {
+    Some(match code {
+        "AccessDenied" | "AllAccessDisabled" | "AccountProblem" | "Forbidden" => ErrorKind::AccessDenied,
+        "InvalidAccessKeyId" | "SignatureDoesNotMatch" | "ExpiredToken" | "InvalidToken" | "TokenRefreshRequired"
+        | "InvalidClientTokenId" | "UnrecognizedClientException" => ErrorKind::InvalidCredentials,
+        "NoSuchBucket" | "NoSuchKey" | "NoSuchUpload" | "NoSuchVersion" | "NoSuchTagSet" | "NoSuchEntity"
+        | "NotFound" => ErrorKind::NotFound,
+        "BucketAlreadyExists" | "BucketAlreadyOwnedByYou" | "EntityAlreadyExists" => ErrorKind::AlreadyExists,
+        "BucketNotEmpty" | "OperationAborted" | "PreconditionFailed" | "ConditionalRequestConflict"
+        | "InvalidObjectState" => ErrorKind::Conflict,
+        "SlowDown" | "Throttling" | "ThrottlingException" | "RequestLimitExceeded" | "TooManyRequests" => {
+            ErrorKind::Throttled
+        }
+        "NotImplemented" | "NotSupported" | "UnsupportedOperation" => ErrorKind::Unsupported,
+        "InternalError" | "ServiceUnavailable" | "ServiceFailure" => ErrorKind::ServiceUnavailable,
+        "RequestTimeout" => ErrorKind::Timeout,
+        // The local clock is off; sending the request again won't help
+        "RequestTimeTooSkewed" => ErrorKind::InvalidRequest,
+        _ => return None,
+    })
+}
+
+fn kind_for_status(status: u16) -> ErrorKind {
+    match status {
+        400 | 405 | 411 | 413 | 416 => ErrorKind::InvalidRequest,
+        401 => ErrorKind::InvalidCredentials,
+        403 => ErrorKind::AccessDenied,
+        404 => ErrorKind::NotFound,
+        408 => ErrorKind::Timeout,
+        409 | 412 => ErrorKind::Conflict,
+        429 => ErrorKind::Throttled,
+        501 => ErrorKind::Unsupported,
+        500..=599 => ErrorKind::ServiceUnavailable,
+        _ => ErrorKind::Internal,
+    }
+}
+
+/// Conversion into a `BucketStackError` without any context
+pub trait Classify {
+    fn classify(self) -> BucketStackError;
+}
+
+impl<E> Classify for SdkError<E, HttpResponse>
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    fn classify(self) -> BucketStackError {
+        let http_status = self.raw_response().map(|r| r.status().as_u16());
+        let request_id = self.request_id().map(str::to_string);
+        let code = self.code().map(str::to_string);
+
+        let kind = match &self {
+            SdkError::TimeoutError(_) => ErrorKind::Timeout,
+            SdkError::DispatchFailure(failure) if failure.is_timeout() => ErrorKind::Timeout,
+            SdkError::DispatchFailure(failure) if failure.is_user() => ErrorKind::InvalidRequest,
+            SdkError::DispatchFailure(_) => ErrorKind::Network,
+            // Usually the credentials provider (profile, role) failed
+            SdkError::ConstructionFailure(_) => ErrorKind::InvalidCredentials,
+            _ => code
+                .as_deref()
+                .and_then(kind_for_code)
+                .or(http_status.map(kind_for_status))
+                .unwrap_or(ErrorKind::Internal),
+        };
+
+        // Prefer the provider's "Code: message" over the SDK's nested display
+        let message = match (code.as_deref(), self.message()) {
+            (Some(code), Some(message)) => format!("{}: {}", code, message),
+            (Some(code), None) => code.to_string(),
+            _ => DisplayErrorContext(&self).to_string(),
+        };
+        let message = match code.as_deref() {
+            Some("RequestTimeTooSkewed") => format!("{} (check that this computer's date, time and time zone are correct)", message),
+            _ => message,
+        };
+
+        let mut error = BucketStackError::new(kind, message);
+        error.retryable |= matches!(code.as_deref(), Some("RequestTimeout" | "InternalError"));
+        BucketStackError { code, http_status, request_id, ..error }
+    }
+}
+
+impl Classify for aws_sdk_s3::primitives::ByteStreamError {
+    fn classify(self) -> BucketStackError {
+        // Body streams fail mid-transfer when the connection drops
+        BucketStackError::new(ErrorKind::Network, DisplayErrorContext(&self).to_string())
+    }
+}
+
+impl Classify for std::io::Error {
+    fn classify(self) -> BucketStackError {
+        let kind = match self.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::PermissionDenied => ErrorKind::AccessDenied,
+            std::io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+            std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            _ => ErrorKind::Io,
+        };
+        let mut error = BucketStackError::new(kind, self.to_string());
+        // Local disk trouble isn't fixed by retrying the same call
+        error.retryable = false;
+        error
+    }
+}
+
+impl Classify for rusqlite::Error {
+    fn classify(self) -> BucketStackError {
+        BucketStackError::new(ErrorKind::Io, self.to_string())
+    }
+}
+
+impl Classify for zip::result::ZipError {
+    fn classify(self) -> BucketStackError {
+        match self {
+            zip::result::ZipError::Io(e) => e.classify(),
+            other => BucketStackError::new(ErrorKind::Io, other.to_string()),
+        }
+    }
+}
+
+// Request builders and presigning reject bad input before anything is sent
+impl Classify for aws_sdk_s3::error::BuildError {
+    fn classify(self) -> BucketStackError {
+        BucketStackError::invalid(self.to_string())
+    }
+}
+
+impl Classify for aws_sdk_s3::presigning::PresigningConfigError {
+    fn classify(self) -> BucketStackError {
+        BucketStackError::invalid(self.to_string())
+    }
+}
+
+impl Classify for std::string::FromUtf8Error {
+    fn classify(self) -> BucketStackError {
+        BucketStackError::invalid(self.to_string())
+    }
+}
+
+impl Classify for serde_json::Error {
+    fn classify(self) -> BucketStackError {
+        BucketStackError::new(ErrorKind::Internal, self.to_string())
+    }
+}
+
+impl Classify for BucketStackError {
+    fn classify(self) -> BucketStackError {
+        self
+    }
+}
+
+impl Classify for String {
+    fn classify(self) -> BucketStackError {
+        self.into()
+    }
+}
+
+impl From<String> for BucketStackError {
+    fn from(message: String) -> Self {
+        BucketStackError::new(ErrorKind::Internal, message)
+    }
+}
+
+impl From<&str> for BucketStackError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl From<CredentialError> for BucketStackError {
+    fn from(e: CredentialError) -> Self {
+        let kind = match &e {
+            CredentialError::Locked => ErrorKind::VaultLocked,
+            CredentialError::IncorrectPassphrase => ErrorKind::IncorrectPassphrase,
+            CredentialError::Quarantined { .. } => ErrorKind::CredentialsQuarantined,
+            CredentialError::UnsupportedVersion { .. } => ErrorKind::UnsupportedCredentialFormat,
+            CredentialError::Storage { .. } => ErrorKind::Io,
+        };
+        BucketStackError::new(kind, e.to_string())
+    }
+}
+
+impl<E> From<SdkError<E, HttpResponse>> for BucketStackError
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    fn from(e: SdkError<E, HttpResponse>) -> Self {
+        e.classify()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_s3::operation::get_object::GetObjectError;
+    use aws_sdk_s3::types::error::NoSuchKey;
+    use aws_sdk_s3::primitives::SdkBody;
+
+    #[test]
+    fn test_service_error_keeps_code_status_and_request_id() {
+        let mut raw = HttpResponse::new(404u16.try_into().unwrap(), SdkBody::empty());
+        raw.headers_mut().insert("x-amz-request-id", "REQ123");
+        let meta = aws_sdk_s3::error::ErrorMetadata::builder()
+            .code("NoSuchKey")
+            .message("The specified key does not exist.")
+            .build();
+        let err = SdkError::service_error(GetObjectError::NoSuchKey(NoSuchKey::builder().meta(meta).build()), raw);
+
+        let error = BucketStackError::wrap("Failed to get object", err);
+        assert_eq!(error.kind, ErrorKind::NotFound);
+        assert_eq!(error.code.as_deref(), Some("NoSuchKey"));
+        assert_eq!(error.http_status, Some(404));
+        assert_eq!(error.request_id.as_deref(), Some("REQ123"));
+        assert!(!error.retryable);
+        assert_eq!(error.message, "Failed to get object: NoSuchKey: The specified key does not exist.");
+    }
+
+    #[test]
+    fn test_archived_objects_and_clock_skew_are_not_retried() {
+        let service_error = |status: u16, code: &str| {
+            let meta = aws_sdk_s3::error::ErrorMetadata::builder().code(code).message("details").build();
+            let raw = HttpResponse::new(status.try_into().unwrap(), SdkBody::empty());
+            BucketStackError::from(SdkError::service_error(GetObjectError::generic(meta), raw))
+        };
+
+        let archived = service_error(403, "InvalidObjectState");
+        assert_eq!(archived.kind, ErrorKind::Conflict);
+        assert!(!archived.retryable);
+
+        let skewed = service_error(403, "RequestTimeTooSkewed");
+        assert_eq!(skewed.kind, ErrorKind::InvalidRequest);
+        assert!(!skewed.retryable);
+        assert!(skewed.message.contains("date, time and time zone"));
+    }
+
+    #[test]
+    fn test_stored_errors_round_trip_and_accept_plain_text() {
+        let mut error = BucketStackError::new(ErrorKind::Throttled, "Slow down");
+        error.code = Some("SlowDown".to_string());
+        assert!(error.retryable);
+        assert_eq!(BucketStackError::from_stored(&error.to_stored()), error);
+
+        let legacy = BucketStackError::from_stored("Failed to upload file: timeout");
+        assert_eq!(legacy.kind, ErrorKind::Internal);
+        assert_eq!(legacy.message, "Failed to upload file: timeout");
+    }
+}
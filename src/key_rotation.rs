@@ -0,0 +1,623 @@
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_iam::types::StatusType;
+use aws_sdk_iam::Client as IamClient;
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::security::{self, CredentialError, SecurityManager};
+use crate::share_links::timestamp;
+use crate::error::{BucketStackError, ErrorKind};
+use crate::connection::ConnectionOptions;
+use crate::{connection, endpoint, log_activity, network, should_log_activity, ActivityLogEntry};
+
+// Global database connection (lazy initialized)
+static KEY_ROTATION_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+// New IAM keys take a few seconds to propagate before S3 accepts them
+const VALIDATION_ATTEMPTS: u32 = 6;
+const VALIDATION_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RotationResult {
+    pub old_access_key_id: String,
+    pub new_access_key_id: String,
+    // Every connection that used the old key; all now use the new one
+    pub updated_connections: Vec<String>,
+    // When the deactivated old key will be deleted (None = already deleted)
+    pub delete_after: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PendingKeyDeletion {
+    pub access_key_id: String,
+    pub connection_id: String,
+    pub region: String,
+    pub bucket_name: String,
+    pub delete_after: String,
+    pub enable_activity_log: bool,
+    // Retry, proxy and TLS settings to reach IAM with, without their secrets
+    #[serde(default)]
+    pub connection: Option<ConnectionOptions>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeyAge {
+    pub connection_id: String,
+    pub access_key_id: String,
+    // None for secrets saved before change tracking existed
+    pub last_changed: Option<String>,
+    pub age_days: Option<i64>,
+}
+
+pub fn init_key_rotation_db(app_data_dir: &Path) -> Result<(), String> {
+    let db_path = app_data_dir.join("key_rotation.db");
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open key rotation database: {}", e))?;
+
+    // When each secure storage entry last got a new value
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS secret_changes (
+            key TEXT PRIMARY KEY,
+            changed_at TEXT NOT NULL
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create secret_changes table: {}", e))?;
+
+    // Deactivated keys waiting out their grace period
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pending_key_deletions (
+            access_key_id TEXT PRIMARY KEY,
+            connection_id TEXT NOT NULL,
+            region TEXT NOT NULL,
+            bucket_name TEXT NOT NULL,
+            delete_after TEXT NOT NULL,
+            enable_activity_log INTEGER NOT NULL,
+            connection_options TEXT
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create pending_key_deletions table: {}", e))?;
+    // Databases from before connection options were kept; fails once it exists
+    let _ = conn.execute("ALTER TABLE pending_key_deletions ADD COLUMN connection_options TEXT", []);
+
+    let mut db = KEY_ROTATION_DB.lock().unwrap();
+    *db = Some(conn);
+
+    Ok(())
+}
+
+pub fn close() {
+    let mut db = KEY_ROTATION_DB.lock().unwrap();
+    *db = None;
+}
+
+// Best effort: a missing timestamp only makes the key show up as "unknown age"
+fn record_secret_change(key: &str) {
+    if let Ok(db_guard) = KEY_ROTATION_DB.lock() {
+        if let Some(conn) = db_guard.as_ref() {
+            let _ = conn.execute(
+                "INSERT OR REPLACE INTO secret_changes (key, changed_at) VALUES (?1, ?2)",
+                params![key, timestamp(Utc::now())],
+            );
+        }
+    }
+}
+
+fn forget_secret(key: &str) {
+    if let Ok(db_guard) = KEY_ROTATION_DB.lock() {
+        if let Some(conn) = db_guard.as_ref() {
+            let _ = conn.execute("DELETE FROM secret_changes WHERE key = ?1", params![key]);
+        }
+    }
+}
+
+/// Store a secret, noting the time only when its value actually changes
+/// (the frontend re-saves unchanged keys whenever a connection is edited)
+pub fn save_secret(manager: &mut dyn SecurityManager, key: String, value: String) -> Result<(), CredentialError> {
+    // An unreadable store must fail the save, not be quarantined behind it
+    let unchanged = manager.get_item(&key)?.as_deref() == Some(value.as_str());
+    manager.set_item(key.clone(), value)?;
+    if !unchanged {
+        record_secret_change(&key);
+    }
+    Ok(())
+}
+
+pub fn delete_secret(manager: &mut dyn SecurityManager, key: &str) -> Result<(), CredentialError> {
+    manager.remove_item(key)?;
+    forget_secret(key);
+    Ok(())
+}
+
+fn secret_changed_at(key: &str) -> Option<DateTime<Utc>> {
+    let db_guard = KEY_ROTATION_DB.lock().ok()?;
+    let conn = db_guard.as_ref()?;
+    let changed_at: String = conn
+        .query_row("SELECT changed_at FROM secret_changes WHERE key = ?1", params![key], |row| row.get(0))
+        .ok()?;
+    DateTime::parse_from_rfc3339(&changed_at).ok().map(|t| t.with_timezone(&Utc))
+}
+
+// (connection id, access key id) for every connection with stored keys
+fn stored_access_keys(manager: &mut dyn SecurityManager) -> Result<Vec<(String, String)>, CredentialError> {
+    let mut keys = Vec::new();
+    for key in manager.keys()? {
+        let Some(id) = key.strip_prefix("bucketstack-").and_then(|k| k.strip_suffix("-access")) else {
+            continue;
+        };
+        if let Some(access_key_id) = manager.get_item(&key)? {
+            keys.push((id.to_string(), access_key_id.trim().to_string()));
+        }
+    }
+    Ok(keys)
+}
+
+/// Connections whose access key hasn't changed in `max_age_days` (or whose
+/// age is unknown), oldest first
+pub fn stale_keys(manager: &mut dyn SecurityManager, max_age_days: i64) -> Result<Vec<KeyAge>, CredentialError> {
+    let now = Utc::now();
+    let mut report: Vec<KeyAge> = stored_access_keys(manager)?
+        .into_iter()
+        .filter_map(|(connection_id, access_key_id)| {
+            // A rotation changes both halves, so the older of the two counts
+            let changed = [
+                secret_changed_at(&format!("bucketstack-{}-access", connection_id)),
+                secret_changed_at(&format!("bucketstack-{}-secret", connection_id)),
+            ]
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .and_then(|times| times.into_iter().min());
+            let age_days = changed.map(|t| (now - t).num_days());
+            if age_days.is_some_and(|age| age < max_age_days) {
+                return None;
+            }
+            Some(KeyAge {
+                connection_id,
+                access_key_id,
+                last_changed: changed.map(timestamp),
+                age_days,
+            })
+        })
+        .collect();
+    // Unknown ages first, then oldest
+    report.sort_by_key(|k| std::cmp::Reverse(k.age_days.unwrap_or(i64::MAX)));
+    Ok(report)
+}
+
+// Replace the old key pair on every connection using it; returns their ids
+fn swap_key(
+    manager: &mut dyn SecurityManager,
+    old_access_key_id: &str,
+    new_access_key_id: &str,
+    new_secret_access_key: &str,
+) -> Result<Vec<String>, CredentialError> {
+    let mut updated = Vec::new();
+    for (connection_id, access_key_id) in stored_access_keys(manager)? {
+        if access_key_id != old_access_key_id {
+            continue;
+        }
+        save_secret(manager, format!("bucketstack-{}-access", connection_id), new_access_key_id.to_string())?;
+        save_secret(manager, format!("bucketstack-{}-secret", connection_id), new_secret_access_key.to_string())?;
+        updated.push(connection_id);
+    }
+    Ok(updated)
+}
+
+// IAM is global but lives in the partition's home region
+fn iam_region(region: &str) -> &'static str {
+    if region.starts_with("cn-") {
+        "cn-north-1"
+    } else if region.starts_with("us-gov-") {
+        "us-gov-west-1"
+    } else {
+        "us-east-1"
+    }
+}
+
+async fn iam_client(
+    region: &str,
+    access_key_id: &str,
+    secret_access_key: &str,
+    options: Option<&ConnectionOptions>,
+) -> Result<IamClient, BucketStackError> {
+    let loader = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new(iam_region(region)))
+        .credentials_provider(connection::static_credentials(access_key_id, secret_access_key, None));
+    let config = network::configure(loader, options)?.load().await;
+    Ok(IamClient::new(&config))
+}
+
+struct StepLogger<'a> {
+    connection_id: &'a str,
+    bucket_name: &'a str,
+    enabled: bool,
+}
+
+impl StepLogger<'_> {
+    fn log(&self, action_type: &str, old_key: &str, new_key: Option<&str>, error: Option<&BucketStackError>) {
+        if !should_log_activity(self.connection_id, self.enabled) {
+            return;
+        }
+        log_activity(ActivityLogEntry {
+            id: None,
+            timestamp: Utc::now().to_rfc3339(),
+            connection_id: self.connection_id.to_string(),
+            provider: "aws".to_string(),
+            bucket_name: self.bucket_name.to_string(),
+            action_type: action_type.to_string(),
+            object_path_before: Some(old_key.to_string()),
+            object_path_after: new_key.map(str::to_string),
+            status: if error.is_some() { "failed" } else { "success" }.to_string(),
+            error_message: error.cloned(),
+            file_size: None,
+            source: "BucketStack".to_string(),
+        });
+    }
+}
+
+// What a later IAM call needs from the connection. Keys come from secure
+// storage at that point, so only transport settings are kept.
+fn transport_options(options: Option<&ConnectionOptions>) -> Option<ConnectionOptions> {
+    let options = options?;
+    let mut proxy = options.proxy.clone();
+    if let Some(proxy) = proxy.as_mut() {
+        proxy.password = None;
+    }
+    let mut tls = options.tls.clone();
+    if let Some(tls) = tls.as_mut() {
+        tls.client_key_pem = None;
+    }
+    Some(ConnectionOptions { network: options.network.clone(), proxy, tls, ..Default::default() })
+}
+
+// Put back the secrets `transport_options` left out, from the entries the
+// frontend keeps next to the connection's keys
+fn with_stored_secrets(manager: &mut dyn SecurityManager, connection_id: &str, mut options: ConnectionOptions) -> Result<ConnectionOptions, CredentialError> {
+    if let Some(proxy) = options.proxy.as_mut() {
+        proxy.password = manager.get_item(&format!("bucketstack-{}-proxy-password", connection_id))?;
+    }
+    if let Some(tls) = options.tls.as_mut() {
+        tls.client_key_pem = manager.get_item(&format!("bucketstack-{}-client-key", connection_id))?;
+    }
+    Ok(options)
+}
+
+fn stored_keys(connection_id: &str) -> Result<(String, String), BucketStackError> {
+    let mut manager = security::get_manager();
+    let access = manager.get_item(&format!("bucketstack-{}-access", connection_id))?;
+    let secret = manager.get_item(&format!("bucketstack-{}-secret", connection_id))?;
+    match (access, secret) {
+        (Some(access), Some(secret)) => Ok((access.trim().to_string(), secret.trim().to_string())),
+        _ => Err(BucketStackError::new(
+            ErrorKind::NotFound,
+            format!("Connection {} has no stored access key", connection_id),
+        )),
+    }
+}
+
+// Why a connection can't be rotated: only plain stored keys are what both IAM
+// and the S3 validation actually sign with
+fn rotation_blocker(options: Option<&ConnectionOptions>) -> Option<&'static str> {
+    let options = options?;
+    if options.session_token.as_deref().is_some_and(|t| !t.trim().is_empty()) {
+        Some("temporary (session token) keys")
+    } else if options.profile.as_deref().is_some_and(|p| !p.is_empty()) {
+        Some("an AWS profile")
+    } else if options.use_default_chain {
+        Some("the default credential chain")
+    } else if options.assume_role.is_some() || options.web_identity.is_some() {
+        Some("a role session")
+    } else {
+        None
+    }
+}
+
+/// Create a new IAM access key for the connection's user, check it against
+/// S3, store it for every connection that used the old key, then deactivate
+/// the old key. It is deleted now (grace of 0) or once the grace period ends.
+pub async fn rotate_access_key(
+    connection_id: &str,
+    endpoint: &str,
+    region: &str,
+    test_bucket: &str,
+    grace_period_hours: u64,
+    enable_activity_log: bool,
+    options: Option<&ConnectionOptions>,
+) -> Result<RotationResult, BucketStackError> {
+    if !endpoint::is_aws_endpoint(endpoint) {
+        return Err(BucketStackError::invalid("Access key rotation is only available for AWS connections"));
+    }
+    if let Some(source) = rotation_blocker(options) {
+        return Err(BucketStackError::invalid(format!(
+            "Access key rotation needs a connection that uses its stored keys directly, not {}",
+            source
+        )));
+    }
+
+    let logger = StepLogger { connection_id, bucket_name: test_bucket, enabled: enable_activity_log };
+    let (old_access_key_id, old_secret) = stored_keys(connection_id)?;
+    let old_iam = iam_client(region, &old_access_key_id, &old_secret, options).await?;
+
+    // 1. Create the replacement (IAM allows two keys per user)
+    let created = old_iam.create_access_key().send().await.map_err(|e| {
+        let error = BucketStackError::wrap("Failed to create access key", e);
+        logger.log("rotate_key_create", &old_access_key_id, None, Some(&error));
+        error
+    })?;
+    let new_key = created.access_key().ok_or("IAM returned no access key")?;
+    let (new_access_key_id, new_secret) = (new_key.access_key_id().to_string(), new_key.secret_access_key().to_string());
+    logger.log("rotate_key_create", &old_access_key_id, Some(&new_access_key_id), None);
+
+    // 2. Validate before anything depends on the new key
+    let mut validation = Ok(());
+    for attempt in 0..VALIDATION_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(VALIDATION_DELAY).await;
+        }
+        validation = crate::test_s3_connection(
+            endpoint.to_string(),
+            region.to_string(),
+            new_access_key_id.clone(),
+            new_secret.clone(),
+            test_bucket.to_string(),
+            options.cloned(),
+        )
+        .await
+        .map(|_| ());
+        if validation.is_ok() {
+            break;
+        }
+    }
+    if let Err(e) = validation {
+        let error = BucketStackError::wrap("New access key failed validation, rotation rolled back", e);
+        logger.log("rotate_key_validate", &old_access_key_id, Some(&new_access_key_id), Some(&error));
+        // Don't leave an unused key behind; the old one is still in place
+        let _ = old_iam.delete_access_key().access_key_id(&new_access_key_id).send().await;
+        return Err(error);
+    }
+    logger.log("rotate_key_validate", &old_access_key_id, Some(&new_access_key_id), None);
+
+    // 3. Swap it into secure storage
+    let updated_connections = swap_key(
+        security::get_manager().as_mut(),
+        &old_access_key_id,
+        &new_access_key_id,
+        &new_secret,
+    )
+    .map_err(|e| {
+        let error = BucketStackError::wrap("Failed to store new access key", BucketStackError::from(e));
+        logger.log("rotate_key_store", &old_access_key_id, Some(&new_access_key_id), Some(&error));
+        error
+    })?;
+    logger.log("rotate_key_store", &old_access_key_id, Some(&new_access_key_id), None);
+
+    // 4. Deactivate the old key; from here on only the new key works
+    let new_iam = iam_client(region, &new_access_key_id, &new_secret, options).await?;
+    if let Err(e) = new_iam
+        .update_access_key()
+        .access_key_id(&old_access_key_id)
+        .status(StatusType::Inactive)
+        .send()
+        .await
+    {
+        let error = BucketStackError::wrap("Failed to deactivate old access key", e);
+        logger.log("rotate_key_deactivate", &old_access_key_id, None, Some(&error));
+        return Err(error);
+    }
+    logger.log("rotate_key_deactivate", &old_access_key_id, None, None);
+
+    // 5. Delete now, or after the grace period
+    let delete_after = if grace_period_hours == 0 {
+        delete_old_key(&new_iam, &logger, &old_access_key_id).await?;
+        None
+    } else {
+        let pending = PendingKeyDeletion {
+            access_key_id: old_access_key_id.clone(),
+            connection_id: connection_id.to_string(),
+            region: region.to_string(),
+            bucket_name: test_bucket.to_string(),
+            delete_after: timestamp(Utc::now() + Duration::hours(grace_period_hours as i64)),
+            enable_activity_log,
+            connection: transport_options(options),
+        };
+        schedule_deletion(&pending)?;
+        Some(pending.delete_after)
+    };
+
+    Ok(RotationResult {
+        old_access_key_id,
+        new_access_key_id,
+        updated_connections,
+        delete_after,
+    })
+}
+
+async fn delete_old_key(iam: &IamClient, logger: &StepLogger<'_>, access_key_id: &str) -> Result<(), BucketStackError> {
+    match iam.delete_access_key().access_key_id(access_key_id).send().await {
+        Ok(_) => {
+            logger.log("rotate_key_delete", access_key_id, None, None);
+            Ok(())
+        }
+        // Someone already removed it in the console
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_entity_exception()) => Ok(()),
+        Err(e) => {
+            let error = BucketStackError::wrap("Failed to delete old access key", e);
+            logger.log("rotate_key_delete", access_key_id, None, Some(&error));
+            Err(error)
+        }
+    }
+}
+
+fn schedule_deletion(pending: &PendingKeyDeletion) -> Result<(), String> {
+    let db_guard = KEY_ROTATION_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Key rotation database not initialized")?;
+    conn.execute(
+        "INSERT OR REPLACE INTO pending_key_deletions (
+            access_key_id, connection_id, region, bucket_name, delete_after, enable_activity_log,
+            connection_options
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            pending.access_key_id,
+            pending.connection_id,
+            pending.region,
+            pending.bucket_name,
+            pending.delete_after,
+            pending.enable_activity_log,
+            pending.connection.as_ref().and_then(|c| serde_json::to_string(c).ok()),
+        ],
+    ).map_err(|e| format!("Failed to schedule key deletion: {}", e))?;
+    Ok(())
+}
+
+pub fn pending_deletions() -> Result<Vec<PendingKeyDeletion>, String> {
+    let db_guard = KEY_ROTATION_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Key rotation database not initialized")?;
+
+    let mut stmt = conn.prepare(
+        "SELECT access_key_id, connection_id, region, bucket_name, delete_after, enable_activity_log,
+                connection_options
+         FROM pending_key_deletions ORDER BY delete_after",
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let pending = stmt.query_map([], |row| {
+        Ok(PendingKeyDeletion {
+            access_key_id: row.get(0)?,
+            connection_id: row.get(1)?,
+            region: row.get(2)?,
+            bucket_name: row.get(3)?,
+            delete_after: row.get(4)?,
+            enable_activity_log: row.get(5)?,
+            connection: row
+                .get::<_, Option<String>>(6)?
+                .and_then(|json| serde_json::from_str(&json).ok()),
+        })
+    })
+    .map_err(|e| format!("Failed to query: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Failed to collect results: {}", e))?;
+
+    Ok(pending)
+}
+
+fn remove_pending(access_key_id: &str) {
+    if let Ok(db_guard) = KEY_ROTATION_DB.lock() {
+        if let Some(conn) = db_guard.as_ref() {
+            let _ = conn.execute("DELETE FROM pending_key_deletions WHERE access_key_id = ?1", params![access_key_id]);
+        }
+    }
+}
+
+/// Delete old keys whose grace period has ended, using the connection's
+/// current (rotated) credentials. Failures stay queued for the next run.
+/// Returns the deleted access key ids.
+pub async fn process_due_deletions() -> Vec<String> {
+    let now = timestamp(Utc::now());
+    let due: Vec<_> = pending_deletions()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|p| p.delete_after <= now)
+        .collect();
+
+    let mut deleted = Vec::new();
+    for pending in due {
+        let logger = StepLogger {
+            connection_id: &pending.connection_id,
+            bucket_name: &pending.bucket_name,
+            enabled: pending.enable_activity_log,
+        };
+        let (access_key_id, secret) = match stored_keys(&pending.connection_id) {
+            Ok(keys) => keys,
+            Err(e) => {
+                // The connection was deleted; there's nothing left to sign with
+                logger.log("rotate_key_delete", &pending.access_key_id, None, Some(&e));
+                remove_pending(&pending.access_key_id);
+                continue;
+            }
+        };
+        let options = match pending.connection.clone() {
+            Some(options) => match with_stored_secrets(security::get_manager().as_mut(), &pending.connection_id, options) {
+                Ok(options) => Some(options),
+                // Locked vault; try again on the next run
+                Err(_) => continue,
+            },
+            None => None,
+        };
+        let iam = match iam_client(&pending.region, &access_key_id, &secret, options.as_ref()).await {
+            Ok(iam) => iam,
+            Err(_) => continue,
+        };
+        if delete_old_key(&iam, &logger, &pending.access_key_id).await.is_ok() {
+            remove_pending(&pending.access_key_id);
+            deleted.push(pending.access_key_id);
+        }
+    }
+    deleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::memory::MemorySecurityManager;
+
+    #[test]
+    fn test_swap_key_updates_every_connection_using_it() {
+        let mut manager = MemorySecurityManager::default();
+        for (id, access) in [("a", "AKIAOLD"), ("b", "AKIAOLD"), ("c", "AKIAOTHER")] {
+            manager.set_item(format!("bucketstack-{}-access", id), access.to_string()).unwrap();
+            manager.set_item(format!("bucketstack-{}-secret", id), "old-secret".to_string()).unwrap();
+        }
+
+        let mut updated = swap_key(&mut manager, "AKIAOLD", "AKIANEW", "new-secret").unwrap();
+        updated.sort();
+        assert_eq!(updated, ["a", "b"]);
+        assert_eq!(manager.get_item("bucketstack-b-access").unwrap().as_deref(), Some("AKIANEW"));
+        assert_eq!(manager.get_item("bucketstack-b-secret").unwrap().as_deref(), Some("new-secret"));
+        assert_eq!(manager.get_item("bucketstack-c-access").unwrap().as_deref(), Some("AKIAOTHER"));
+    }
+
+    #[test]
+    fn test_only_plain_stored_keys_can_be_rotated() {
+        use crate::network::NetworkPolicy;
+        assert_eq!(rotation_blocker(None), None);
+        let plain = ConnectionOptions { network: Some(NetworkPolicy::default()), ..Default::default() };
+        assert_eq!(rotation_blocker(Some(&plain)), None);
+
+        let temporary = ConnectionOptions { session_token: Some("FwoGZXIvYXdzE".to_string()), ..Default::default() };
+        assert!(rotation_blocker(Some(&temporary)).is_some());
+        let profile = ConnectionOptions { profile: Some("work".to_string()), ..Default::default() };
+        assert!(rotation_blocker(Some(&profile)).is_some());
+        let chain = ConnectionOptions { use_default_chain: true, ..Default::default() };
+        assert!(rotation_blocker(Some(&chain)).is_some());
+        let role = ConnectionOptions { assume_role: Some(Default::default()), ..Default::default() };
+        assert!(rotation_blocker(Some(&role)).is_some());
+    }
+
+    #[test]
+    fn test_pending_deletion_keeps_transport_settings_without_secrets() {
+        use crate::network::{ProxySettings, TlsSettings};
+        let options = ConnectionOptions {
+            session_token: Some("FwoGZXIvYXdzE".to_string()),
+            proxy: Some(ProxySettings {
+                url: "http://proxy.corp:3128".to_string(),
+                username: Some("me".to_string()),
+                password: Some("hunter2".to_string()),
+                no_proxy: None,
+            }),
+            tls: Some(TlsSettings { client_key_pem: Some("KEY".to_string()), ..Default::default() }),
+            ..Default::default()
+        };
+        let kept = transport_options(Some(&options)).unwrap();
+        let json = serde_json::to_string(&kept).unwrap();
+        assert!(!json.contains("hunter2") && !json.contains("KEY") && !json.contains("FwoGZXIvYXdzE"));
+
+        let mut manager = MemorySecurityManager::default();
+        manager.set_item("bucketstack-a-proxy-password".to_string(), "hunter2".to_string()).unwrap();
+        manager.set_item("bucketstack-a-client-key".to_string(), "KEY".to_string()).unwrap();
+        let restored = with_stored_secrets(&mut manager, "a", serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(restored.proxy.unwrap().password.as_deref(), Some("hunter2"));
+        assert_eq!(restored.tls.unwrap().client_key_pem.as_deref(), Some("KEY"));
+    }
+}
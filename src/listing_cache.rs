@@ -0,0 +1,228 @@
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Global database connection (lazy initialized)
+static LISTING_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+// Listings with a background refresh running, so navigating back and forth
+// doesn't start one per visit
+static REVALIDATING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// Younger listings are served without asking the provider again
+const FRESH_SECONDS: i64 = 5;
+const RETENTION_DAYS: i64 = 30;
+
+/// A stored `list_objects` page. `body` is the serialized response; the
+/// cache doesn't look inside it.
+#[derive(Debug, Clone)]
+pub struct CachedListing {
+    pub body: String,
+    pub fetched_at: DateTime<Utc>,
+    // One of our own writes touched this prefix; only served when offline
+    pub invalidated: bool,
+}
+
+impl CachedListing {
+    pub fn is_fresh(&self) -> bool {
+        !self.invalidated && Utc::now() - self.fetched_at < Duration::seconds(FRESH_SECONDS)
+    }
+}
+
+fn endpoint_key(endpoint: &str) -> String {
+    endpoint.trim().trim_end_matches('/').to_string()
+}
+
+pub fn init_listing_cache_db(app_data_dir: &Path) -> Result<(), String> {
+    let db_path = app_data_dir.join("listing_cache.db");
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open listing cache database: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS listings (
+            connection_key TEXT NOT NULL,
+            endpoint TEXT NOT NULL,
+            bucket TEXT NOT NULL,
+            prefix TEXT NOT NULL,
+            fetched_at TEXT NOT NULL,
+            invalidated INTEGER NOT NULL DEFAULT 0,
+            -- Unix milliseconds of the last invalidation
+            invalidated_at INTEGER,
+            body TEXT NOT NULL,
+            PRIMARY KEY (connection_key, bucket, prefix)
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create listings table: {}", e))?;
+    // Caches created before invalidations were timestamped; fails once it exists
+    let _ = conn.execute("ALTER TABLE listings ADD COLUMN invalidated_at INTEGER", []);
+
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_listings_bucket ON listings(endpoint, bucket)",
+        [],
+    );
+    let _ = conn.execute(
+        "DELETE FROM listings WHERE fetched_at < ?1",
+        params![(Utc::now() - Duration::days(RETENTION_DAYS)).to_rfc3339()],
+    );
+
+    let mut db = LISTING_DB.lock().unwrap();
+    *db = Some(conn);
+
+    Ok(())
+}
+
+pub fn close() {
+    let mut db = LISTING_DB.lock().unwrap();
+    *db = None;
+}
+
+pub fn get(connection_key: &str, bucket: &str, prefix: &str) -> Option<CachedListing> {
+    let db_guard = LISTING_DB.lock().unwrap();
+    let conn = db_guard.as_ref()?;
+    let (body, fetched_at, invalidated): (String, String, bool) = conn
+        .query_row(
+            "SELECT body, fetched_at, invalidated FROM listings WHERE connection_key = ?1 AND bucket = ?2 AND prefix = ?3",
+            params![connection_key, bucket, prefix],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .ok()??;
+    Some(CachedListing {
+        body,
+        fetched_at: DateTime::parse_from_rfc3339(&fetched_at).ok()?.with_timezone(&Utc),
+        invalidated,
+    })
+}
+
+/// Unix milliseconds to pass to `put` as the start of a fetch
+pub fn fetch_started() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Store a listing fetched from `started` on, unless one of our writes
+/// invalidated it meanwhile and the fetch may have missed it.
+/// Returns whether it was stored.
+pub fn put(connection_key: &str, endpoint: &str, bucket: &str, prefix: &str, body: &str, started: i64) -> bool {
+    let db_guard = LISTING_DB.lock().unwrap();
+    let Some(conn) = db_guard.as_ref() else {
+        return false;
+    };
+    let result = conn.execute(
+        "INSERT OR REPLACE INTO listings (connection_key, endpoint, bucket, prefix, fetched_at, invalidated, body)
+         SELECT ?1, ?2, ?3, ?4, ?5, 0, ?6
+         WHERE NOT EXISTS (
+             SELECT 1 FROM listings
+             WHERE connection_key = ?1 AND bucket = ?3 AND prefix = ?4 AND invalidated_at >= ?7
+         )",
+        params![connection_key, endpoint_key(endpoint), bucket, prefix, Utc::now().to_rfc3339(), body, started],
+    );
+    match result {
+        Ok(stored) => stored > 0,
+        Err(e) => {
+            eprintln!("Failed to cache listing: {}", e);
+            false
+        }
+    }
+}
+
+fn mark(sql: &str, endpoint: &str, bucket: &str, path: &str) {
+    let db_guard = LISTING_DB.lock().unwrap();
+    if let Some(conn) = db_guard.as_ref() {
+        let _ = conn.execute(sql, params![endpoint_key(endpoint), bucket, path, Utc::now().timestamp_millis()]);
+    }
+}
+
+/// A write to `key` changes the listing of every prefix it starts with
+/// (its folder and, for new folders, their parents)
+pub fn invalidate_key(endpoint: &str, bucket: &str, key: &str) {
+    mark(
+        "UPDATE listings SET invalidated = 1, invalidated_at = ?4
+         WHERE endpoint = ?1 AND bucket = ?2 AND substr(?3, 1, length(prefix)) = prefix",
+        endpoint,
+        bucket,
+        key,
+    );
+}
+
+/// Everything under `prefix` changed (folder delete, move or copy)
+pub fn invalidate_prefix(endpoint: &str, bucket: &str, prefix: &str) {
+    mark(
+        "UPDATE listings SET invalidated = 1, invalidated_at = ?4
+         WHERE endpoint = ?1 AND bucket = ?2
+         AND (substr(?3, 1, length(prefix)) = prefix OR substr(prefix, 1, length(?3)) = ?3)",
+        endpoint,
+        bucket,
+        prefix,
+    );
+}
+
+pub fn forget_bucket(endpoint: &str, bucket: &str) {
+    let db_guard = LISTING_DB.lock().unwrap();
+    if let Some(conn) = db_guard.as_ref() {
+        let _ = conn.execute(
+            "DELETE FROM listings WHERE endpoint = ?1 AND bucket = ?2",
+            params![endpoint_key(endpoint), bucket],
+        );
+    }
+}
+
+/// Claim the background refresh of a listing and note when it started for
+/// `put`; None if one is already running
+pub fn begin_revalidation(connection_key: &str, bucket: &str, prefix: &str) -> Option<i64> {
+    let started = fetch_started();
+    REVALIDATING
+        .lock()
+        .unwrap()
+        .insert(format!("{}|{}|{}", connection_key, bucket, prefix))
+        .then_some(started)
+}
+
+pub fn end_revalidation(connection_key: &str, bucket: &str, prefix: &str) {
+    REVALIDATING.lock().unwrap().remove(&format!("{}|{}|{}", connection_key, bucket, prefix));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes_invalidate_enclosing_prefixes() {
+        let dir = std::env::temp_dir().join(format!("bucketstack-listing-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        init_listing_cache_db(&dir).unwrap();
+
+        let endpoint = "http://minio.test:9000";
+        for prefix in ["", "docs/", "docs/2024/", "photos/"] {
+            put("conn", endpoint, "b", prefix, "[]", fetch_started());
+        }
+        assert!(get("conn", "b", "docs/").unwrap().is_fresh());
+
+        invalidate_key(endpoint, "b", "docs/2024/report.pdf");
+        let invalidated = |prefix: &str| get("conn", "b", prefix).unwrap().invalidated;
+        assert!(invalidated("") && invalidated("docs/") && invalidated("docs/2024/"));
+        assert!(!invalidated("photos/"));
+
+        put("conn", endpoint, "b", "photos/", "[]", fetch_started());
+        put("conn", endpoint, "b", "photos/old/", "[]", fetch_started());
+        invalidate_prefix(endpoint, "b", "photos/");
+        assert!(invalidated("photos/") && invalidated("photos/old/"));
+
+        // A refresh that was in flight during a write doesn't overwrite the invalidation
+        let started = begin_revalidation("conn", "b", "docs/").unwrap();
+        assert!(begin_revalidation("conn", "b", "docs/").is_none());
+        invalidate_key(endpoint, "b", "docs/new.txt");
+        assert!(!put("conn", endpoint, "b", "docs/", "[\"stale\"]", started));
+        assert!(invalidated("docs/"));
+        end_revalidation("conn", "b", "docs/");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let started = begin_revalidation("conn", "b", "docs/").unwrap();
+        assert!(put("conn", endpoint, "b", "docs/", "[]", started));
+        assert!(get("conn", "b", "docs/").unwrap().is_fresh());
+        end_revalidation("conn", "b", "docs/");
+
+        close();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
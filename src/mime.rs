@@ -0,0 +1,258 @@
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+const SETTINGS_FILE: &str = "mime_types.json";
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+// Enough for every signature `infer` knows about
+const SNIFF_LEN: usize = 8192;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MimeSettings {
+    // Extension (without the dot, lowercase) -> Content-Type; wins over detection
+    pub overrides: HashMap<String, String>,
+    // Serve `app.js.gz` as `text/javascript` + `Content-Encoding: gzip`
+    // instead of as an opaque `application/gzip` download
+    pub detect_content_encoding: bool,
+}
+
+impl Default for MimeSettings {
+    fn default() -> Self {
+        let overrides = [
+            ("wasm", "application/wasm"),
+            ("mjs", "text/javascript"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        Self { overrides, detect_content_encoding: false }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DetectedType {
+    pub content_type: String,
+    pub content_encoding: Option<String>,
+}
+
+static SETTINGS: Lazy<RwLock<MimeSettings>> = Lazy::new(|| RwLock::new(load_settings()));
+
+fn get_settings_path() -> PathBuf {
+    match ProjectDirs::from("com", "bucketstack", "app") {
+        Some(proj_dirs) => proj_dirs.config_dir().join(SETTINGS_FILE),
+        None => PathBuf::from(SETTINGS_FILE),
+    }
+}
+
+fn load_settings() -> MimeSettings {
+    let path = get_settings_path();
+    match fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Warning: Invalid MIME settings file, using defaults: {}", e);
+            MimeSettings::default()
+        }),
+        Err(_) => MimeSettings::default(),
+    }
+}
+
+pub fn settings() -> MimeSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+pub fn save_settings(mut new_settings: MimeSettings) -> Result<(), String> {
+    // Normalise keys so ".WASM" and "wasm" mean the same thing
+    new_settings.overrides = new_settings
+        .overrides
+        .into_iter()
+        .map(|(ext, ty)| (ext.trim_start_matches('.').to_lowercase(), ty.trim().to_string()))
+        .filter(|(ext, ty)| !ext.is_empty() && !ty.is_empty())
+        .collect();
+
+    let path = get_settings_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&new_settings).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write MIME settings: {}", e))?;
+
+    *SETTINGS.write().unwrap() = new_settings;
+    Ok(())
+}
+
+fn extension_of(name: &str) -> Option<String> {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+}
+
+// Type from the file name alone: overrides first, then the extension database
+fn type_from_name(name: &str, settings: &MimeSettings) -> Option<String> {
+    let ext = extension_of(name)?;
+    if let Some(ty) = settings.overrides.get(&ext) {
+        return Some(ty.clone());
+    }
+    mime_guess::from_ext(&ext).first().map(|m| m.essence_str().to_string())
+}
+
+/// Detect the Content-Type (and optionally Content-Encoding) for an object.
+/// `name` is a file name or object key, `head` the first bytes of its content
+/// (may be empty) used for magic-byte sniffing when the name doesn't help.
+pub fn detect_with(name: &str, head: &[u8], settings: &MimeSettings) -> DetectedType {
+    if let Some(detected) = precompressed(name, settings) {
+        return detected;
+    }
+
+    let content_type = type_from_name(name, settings)
+        .or_else(|| infer::get(head).map(|t| t.mime_type().to_string()))
+        .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+
+    DetectedType { content_type, content_encoding: None }
+}
+
+// `app.js.gz` as `text/javascript` + gzip, when encoding detection is on
+fn precompressed(name: &str, settings: &MimeSettings) -> Option<DetectedType> {
+    if !settings.detect_content_encoding {
+        return None;
+    }
+    let encoding = match extension_of(name).as_deref() {
+        Some("gz") => "gzip",
+        Some("br") => "br",
+        _ => return None,
+    };
+    // Only web assets; `backup.tar.gz` must stay a gzip download, or clients
+    // would transparently unpack it
+    let inner = name.rfind('.').map(|i| &name[..i]).unwrap_or(name);
+    let content_type = type_from_name(inner, settings).filter(|ct| is_web_asset(ct))?;
+    Some(DetectedType { content_type, content_encoding: Some(encoding.to_string()) })
+}
+
+// Types that browsers fetch pre-compressed: text, scripts, styles, data, SVG, wasm
+fn is_web_asset(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/javascript" | "application/json" | "application/xml" | "application/wasm"
+        )
+}
+
+/// Type for an upload whose caller may have supplied one. Overrides and
+/// pre-compressed assets still win, since the caller's guess (e.g. a browser's
+/// `application/gzip` for `app.js.gz`) doesn't know about them.
+pub fn resolve_with(name: &str, head: &[u8], supplied: &str, settings: &MimeSettings) -> DetectedType {
+    if let Some(detected) = precompressed(name, settings) {
+        return detected;
+    }
+    if let Some(content_type) = extension_of(name).and_then(|ext| settings.overrides.get(&ext).cloned()) {
+        return DetectedType { content_type, content_encoding: None };
+    }
+    if is_unspecified(supplied) {
+        return detect_with(name, head, settings);
+    }
+    DetectedType { content_type: supplied.trim().to_string(), content_encoding: None }
+}
+
+pub fn resolve(name: &str, head: &[u8], supplied: &str) -> DetectedType {
+    resolve_with(name, head, supplied, &SETTINGS.read().unwrap())
+}
+
+pub fn detect(name: &str, head: &[u8]) -> DetectedType {
+    detect_with(name, head, &SETTINGS.read().unwrap())
+}
+
+/// Detect the type of a local file, sniffing its first few KB as a fallback
+pub fn detect_file(path: &Path) -> DetectedType {
+    let name = path.to_string_lossy();
+    let mut head = Vec::new();
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.take(SNIFF_LEN as u64).read_to_end(&mut head);
+    }
+    detect(&name, &head)
+}
+
+/// True when the caller didn't supply a meaningful Content-Type
+pub fn is_unspecified(content_type: &str) -> bool {
+    let ct = content_type.trim();
+    ct.is_empty() || ct.eq_ignore_ascii_case(DEFAULT_CONTENT_TYPE) || ct.eq_ignore_ascii_case("binary/octet-stream")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_MAGIC: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D, b'I', b'H', b'D', b'R'];
+
+    #[test]
+    fn test_detects_from_extension() {
+        let settings = MimeSettings::default();
+        assert_eq!(detect_with("site/index.html", &[], &settings).content_type, "text/html");
+        assert_eq!(detect_with("styles/MAIN.CSS", &[], &settings).content_type, "text/css");
+    }
+
+    #[test]
+    fn test_overrides_win_over_database() {
+        let mut settings = MimeSettings::default();
+        settings.overrides.insert("js".to_string(), "application/x-custom".to_string());
+        assert_eq!(detect_with("app.js", &[], &settings).content_type, "application/x-custom");
+        assert_eq!(detect_with("pkg/module.wasm", &[], &settings).content_type, "application/wasm");
+    }
+
+    #[test]
+    fn test_sniffs_magic_bytes_without_extension() {
+        let settings = MimeSettings::default();
+        assert_eq!(detect_with("uploads/photo", PNG_MAGIC, &settings).content_type, "image/png");
+        assert_eq!(detect_with("uploads/blob", b"???", &settings).content_type, DEFAULT_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn test_content_encoding_detection() {
+        let mut settings = MimeSettings::default();
+        assert_eq!(
+            detect_with("dist/app.js.gz", &[], &settings),
+            DetectedType { content_type: "application/gzip".to_string(), content_encoding: None }
+        );
+
+        settings.detect_content_encoding = true;
+        assert_eq!(
+            detect_with("dist/app.js.gz", &[], &settings),
+            DetectedType { content_type: "text/javascript".to_string(), content_encoding: Some("gzip".to_string()) }
+        );
+        assert_eq!(
+            detect_with("dist/index.html.br", &[], &settings),
+            DetectedType { content_type: "text/html".to_string(), content_encoding: Some("br".to_string()) }
+        );
+        // A plain archive has no inner type, so it stays a gzip download
+        assert_eq!(detect_with("backup.gz", &[], &settings).content_encoding, None);
+        // Neither do compressed archives, which clients would otherwise unpack
+        for archive in ["backup.tar.gz", "initrd.cpio.br"] {
+            assert_eq!(detect_with(archive, &[], &settings).content_encoding, None);
+            assert_eq!(resolve_with(archive, &[], "", &settings).content_encoding, None);
+        }
+        assert_eq!(detect_with("icons/logo.svg.gz", &[], &settings).content_type, "image/svg+xml");
+        assert_eq!(detect_with("pkg/module.wasm.br", &[], &settings).content_encoding.as_deref(), Some("br"));
+    }
+
+    #[test]
+    fn test_supplied_type_yields_to_settings() {
+        let settings = MimeSettings { detect_content_encoding: true, ..MimeSettings::default() };
+        assert_eq!(
+            resolve_with("dist/app.js.gz", &[], "application/gzip", &settings),
+            DetectedType { content_type: "text/javascript".to_string(), content_encoding: Some("gzip".to_string()) }
+        );
+        assert_eq!(resolve_with("pkg/module.wasm", &[], "application/octet-stream", &settings).content_type, "application/wasm");
+        assert_eq!(resolve_with("pkg/module.wasm", &[], "text/plain", &settings).content_type, "application/wasm");
+        // Without a setting for it, the caller's type beats the extension database
+        assert_eq!(resolve_with("notes.txt", &[], "text/markdown", &settings).content_type, "text/markdown");
+        assert_eq!(resolve_with("uploads/photo", PNG_MAGIC, "", &settings).content_type, "image/png");
+    }
+}
@@ -0,0 +1,413 @@
+use aws_config::retry::RetryConfig;
+use aws_config::timeout::TimeoutConfig;
+use aws_config::ConfigLoader;
+use aws_sdk_s3::primitives::SdkBody;
+use aws_smithy_runtime_api::client::http::{
+    http_client_fn, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpClient,
+    SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::HttpRequest;
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_runtime_api::http::Response as HttpResponse;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::connection::ConnectionOptions;
+use crate::error::{BucketStackError, ErrorKind};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryMode {
+    // Exponential backoff with jitter
+    #[default]
+    Standard,
+    // Standard plus client-side rate limiting once the server starts throttling
+    Adaptive,
+}
+
+/// Retry, timeout and connection-pool settings for a connection. Anything
+/// left unset keeps the SDK default (3 attempts, 3.1s connect timeout, no
+/// read or operation timeout).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NetworkPolicy {
+    // Attempts including the first one; 1 disables retries
+    pub max_attempts: Option<u32>,
+    pub retry_mode: Option<RetryMode>,
+    pub connect_timeout_secs: Option<u64>,
+    // Longest wait for the next bytes of a response
+    pub read_timeout_secs: Option<u64>,
+    // Whole call including retries
+    pub operation_timeout_secs: Option<u64>,
+    pub operation_attempt_timeout_secs: Option<u64>,
+    // Close pooled connections after this long unused
+    pub pool_idle_timeout_secs: Option<u64>,
+}
+
+impl NetworkPolicy {
+    /// Field-by-field override, e.g. a single huge upload that needs a longer
+    /// operation timeout than the connection normally uses
+    pub fn overridden_by(&self, other: &NetworkPolicy) -> NetworkPolicy {
+        NetworkPolicy {
+            max_attempts: other.max_attempts.or(self.max_attempts),
+            retry_mode: other.retry_mode.or(self.retry_mode),
+            connect_timeout_secs: other.connect_timeout_secs.or(self.connect_timeout_secs),
+            read_timeout_secs: other.read_timeout_secs.or(self.read_timeout_secs),
+            operation_timeout_secs: other.operation_timeout_secs.or(self.operation_timeout_secs),
+            operation_attempt_timeout_secs: other
+                .operation_attempt_timeout_secs
+                .or(self.operation_attempt_timeout_secs),
+            pool_idle_timeout_secs: other.pool_idle_timeout_secs.or(self.pool_idle_timeout_secs),
+        }
+    }
+
+    fn retry_config(&self) -> Option<RetryConfig> {
+        if self.max_attempts.is_none() && self.retry_mode.is_none() {
+            return None;
+        }
+        let config = match self.retry_mode.unwrap_or_default() {
+            RetryMode::Standard => RetryConfig::standard(),
+            RetryMode::Adaptive => RetryConfig::adaptive(),
+        };
+        Some(match self.max_attempts {
+            Some(attempts) => config.with_max_attempts(attempts.max(1)),
+            None => config,
+        })
+    }
+
+    // Unset fields are filled in from the SDK defaults by the config loader
+    fn timeout_config(&self) -> Option<TimeoutConfig> {
+        let secs = |s: Option<u64>| s.map(Duration::from_secs);
+        let mut builder = TimeoutConfig::builder();
+        builder
+            .set_connect_timeout(secs(self.connect_timeout_secs))
+            .set_read_timeout(secs(self.read_timeout_secs))
+            .set_operation_timeout(secs(self.operation_timeout_secs))
+            .set_operation_attempt_timeout(secs(self.operation_attempt_timeout_secs));
+        let config = builder.build();
+        config.has_timeouts().then_some(config)
+    }
+
+    fn apply(&self, mut loader: ConfigLoader) -> ConfigLoader {
+        if let Some(retry) = self.retry_config() {
+            loader = loader.retry_config(retry);
+        }
+        if let Some(timeouts) = self.timeout_config() {
+            loader = loader.timeout_config(timeouts);
+        }
+        loader
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProxySettings {
+    // http://host:port or https://host:port
+    pub url: String,
+    pub username: Option<String>,
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    // Comma-separated hosts, domains (.corp.example) and CIDRs to reach directly
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TlsSettings {
+    // Extra trusted CA certificates (PEM), on top of the system roots
+    pub ca_certificates_pem: Option<String>,
+    // Client certificate chain and its private key (PEM) for mutual TLS
+    pub client_certificate_pem: Option<String>,
+    #[serde(skip_serializing)]
+    pub client_key_pem: Option<String>,
+    // Accept any server certificate. Only for lab setups: anyone on the
+    // network path can read and change the traffic.
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+/// Apply the connection's retry/timeout policy, proxy and TLS settings to an
+/// SDK config loader. Every client we build (S3, STS, IAM) goes through here.
+pub fn configure(mut loader: ConfigLoader, options: Option<&ConnectionOptions>) -> Result<ConfigLoader, BucketStackError> {
+    let Some(options) = options else {
+        return Ok(loader);
+    };
+    if let Some(policy) = &options.network {
+        loader = policy.apply(loader);
+    }
+    if let Some(client) = http_client(options)? {
+        loader = loader.http_client(client);
+    }
+    Ok(loader)
+}
+
+/// Plain HTTP client with the connection's proxy and TLS settings, for
+/// requests made outside the SDK (fetching a presigned URL)
+pub fn reqwest_client(options: Option<&ConnectionOptions>) -> Result<reqwest::Client, BucketStackError> {
+    let options = options.cloned().unwrap_or_default();
+    let pool_idle_timeout = options.network.as_ref().and_then(|n| n.pool_idle_timeout_secs);
+    Transport::new(pool_idle_timeout, options.proxy.as_ref(), options.tls.as_ref())?
+        .build_client((None, None))
+        .map_err(|e| BucketStackError::new(ErrorKind::Internal, format!("Failed to create HTTP client: {}", e)))
+}
+
+pub fn skips_tls_verification(options: Option<&ConnectionOptions>) -> bool {
+    options.and_then(|o| o.tls.as_ref()).is_some_and(|t| t.insecure_skip_verify)
+}
+
+/// Connection options for one transfer, with `transfer` layered over the
+/// connection's own policy
+pub fn with_transfer_policy(
+    connection: Option<ConnectionOptions>,
+    transfer: Option<NetworkPolicy>,
+) -> Option<ConnectionOptions> {
+    let Some(transfer) = transfer else {
+        return connection;
+    };
+    let mut connection = connection.unwrap_or_default();
+    connection.network = Some(connection.network.unwrap_or_default().overridden_by(&transfer));
+    Some(connection)
+}
+
+// SDK clients are built per command, but the connection pool lives in the
+// HTTP client, so share one per transport configuration or idle connections
+// are never reused
+static HTTP_CLIENTS: Lazy<Mutex<HashMap<String, SharedHttpClient>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// None keeps the SDK's default HTTPS client
+fn http_client(options: &ConnectionOptions) -> Result<Option<SharedHttpClient>, BucketStackError> {
+    let pool_idle_timeout = options.network.as_ref().and_then(|n| n.pool_idle_timeout_secs);
+    if pool_idle_timeout.is_none() && options.proxy.is_none() && options.tls.is_none() {
+        return Ok(None);
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{:?}{:?}{:?}", pool_idle_timeout, options.proxy, options.tls).as_bytes());
+    let key = hex::encode(hasher.finalize());
+
+    let mut clients = HTTP_CLIENTS.lock().unwrap();
+    if let Some(client) = clients.get(&key) {
+        return Ok(Some(client.clone()));
+    }
+
+    let transport = Arc::new(Transport::new(pool_idle_timeout, options.proxy.as_ref(), options.tls.as_ref())?);
+    let client = http_client_fn(move |settings, _| SharedHttpConnector::new(transport.connector(settings)));
+    clients.insert(key, client.clone());
+    Ok(Some(client))
+}
+
+// (connect, read) timeouts from the SDK's connector settings
+type Timeouts = (Option<Duration>, Option<Duration>);
+
+// Parsed proxy/TLS settings; reqwest clients are built from these per
+// connect/read timeout pair the SDK asks for
+struct Transport {
+    pool_idle_timeout: Option<Duration>,
+    proxy: Option<reqwest::Proxy>,
+    ca_certificates: Vec<reqwest::Certificate>,
+    identity: Option<reqwest::Identity>,
+    insecure_skip_verify: bool,
+    clients: Mutex<HashMap<Timeouts, reqwest::Client>>,
+}
+
+impl Transport {
+    fn new(
+        pool_idle_timeout_secs: Option<u64>,
+        proxy: Option<&ProxySettings>,
+        tls: Option<&TlsSettings>,
+    ) -> Result<Self, BucketStackError> {
+        let proxy = match proxy.filter(|p| !p.url.trim().is_empty()) {
+            Some(settings) => {
+                let mut proxy = reqwest::Proxy::all(settings.url.trim())
+                    .map_err(|e| BucketStackError::invalid(format!("Invalid proxy URL: {}", e)))?;
+                if let Some(username) = settings.username.as_deref().filter(|u| !u.is_empty()) {
+                    proxy = proxy.basic_auth(username, settings.password.as_deref().unwrap_or(""));
+                }
+                let no_proxy = settings.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string);
+                Some(proxy.no_proxy(no_proxy))
+            }
+            None => None,
+        };
+
+        let tls = tls.cloned().unwrap_or_default();
+        let ca_certificates = match tls.ca_certificates_pem.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(pem) => {
+                let certs = reqwest::Certificate::from_pem_bundle(pem.as_bytes())
+                    .map_err(|e| BucketStackError::invalid(format!("Invalid CA certificate: {}", e)))?;
+                if certs.is_empty() {
+                    return Err(BucketStackError::invalid("No certificates found in the CA bundle"));
+                }
+                certs
+            }
+            None => Vec::new(),
+        };
+        let identity = match (tls.client_certificate_pem.as_deref(), tls.client_key_pem.as_deref()) {
+            (Some(cert), Some(key)) if !cert.trim().is_empty() => {
+                let pem = format!("{}\n{}", cert.trim(), key.trim());
+                Some(
+                    reqwest::Identity::from_pem(pem.as_bytes())
+                        .map_err(|e| BucketStackError::invalid(format!("Invalid client certificate or key: {}", e)))?,
+                )
+            }
+            (Some(cert), None) if !cert.trim().is_empty() => {
+                return Err(BucketStackError::invalid("A client certificate needs its private key"));
+            }
+            _ => None,
+        };
+
+        if tls.insecure_skip_verify {
+            eprintln!(
+                "WARNING: TLS certificate verification is DISABLED for a connection. \
+                 Its traffic can be read and modified by anyone on the network path."
+            );
+        }
+
+        let transport = Transport {
+            pool_idle_timeout: pool_idle_timeout_secs.map(Duration::from_secs),
+            proxy,
+            ca_certificates,
+            identity,
+            insecure_skip_verify: tls.insecure_skip_verify,
+            clients: Mutex::new(HashMap::new()),
+        };
+        // Certificates and keys are only parsed when a client is built
+        transport
+            .build_client((None, None))
+            .map_err(|e| BucketStackError::invalid(format!("Invalid proxy or TLS settings: {}", e)))?;
+        Ok(transport)
+    }
+
+    fn connector(&self, settings: &HttpConnectorSettings) -> ReqwestConnector {
+        let timeouts: Timeouts = (settings.connect_timeout(), settings.read_timeout());
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&timeouts) {
+            return ReqwestConnector { client: Ok(client.clone()) };
+        }
+
+        let client = self
+            .build_client(timeouts)
+            .map_err(|e| format!("Failed to create HTTP client: {}", e));
+        if let Ok(client) = &client {
+            clients.insert(timeouts, client.clone());
+        }
+        ReqwestConnector { client }
+    }
+
+    fn build_client(&self, timeouts: Timeouts) -> reqwest::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tls_certs_merge(self.ca_certificates.clone())
+            .tls_danger_accept_invalid_certs(self.insecure_skip_verify);
+        if let Some(connect) = timeouts.0 {
+            builder = builder.connect_timeout(connect);
+        }
+        if let Some(read) = timeouts.1 {
+            builder = builder.read_timeout(read);
+        }
+        builder = match &self.proxy {
+            Some(proxy) => builder.proxy(proxy.clone()),
+            // Only an explicitly configured proxy, never HTTP(S)_PROXY
+            None => builder.no_proxy(),
+        };
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.clone());
+        }
+        builder.build()
+    }
+}
+
+#[derive(Debug)]
+struct ReqwestConnector {
+    client: Result<reqwest::Client, String>,
+}
+
+impl HttpConnector for ReqwestConnector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let client = self.client.clone();
+        HttpConnectorFuture::new(async move {
+            let client = client.map_err(|e| ConnectorError::user(e.into()))?;
+            let request = request
+                .try_into_http1x()
+                .map_err(|e| ConnectorError::user(e.into()))?
+                .map(reqwest::Body::wrap);
+            let request = reqwest::Request::try_from(request).map_err(|e| ConnectorError::user(e.into()))?;
+
+            let response = client.execute(request).await.map_err(|e| {
+                if e.is_timeout() {
+                    ConnectorError::timeout(e.into())
+                } else if e.is_connect() {
+                    ConnectorError::io(e.into())
+                } else {
+                    ConnectorError::other(e.into(), None)
+                }
+            })?;
+
+            let response = http::Response::from(response).map(SdkBody::from_body_1_x);
+            HttpResponse::try_from(response).map_err(|e| ConnectorError::other(e.into(), None))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_and_derived_configs() {
+        let connection = NetworkPolicy {
+            max_attempts: Some(5),
+            retry_mode: Some(RetryMode::Adaptive),
+            connect_timeout_secs: Some(2),
+            operation_timeout_secs: Some(60),
+            ..Default::default()
+        };
+        let transfer = NetworkPolicy { operation_timeout_secs: Some(3600), ..Default::default() };
+
+        let merged = connection.overridden_by(&transfer);
+        assert_eq!(merged.max_attempts, Some(5));
+        assert_eq!(merged.operation_timeout_secs, Some(3600));
+
+        let retry = merged.retry_config().unwrap();
+        assert_eq!(retry.max_attempts(), 5);
+        assert_eq!(retry.mode(), aws_config::retry::RetryMode::Adaptive);
+
+        let timeouts = merged.timeout_config().unwrap();
+        assert_eq!(timeouts.connect_timeout(), Some(Duration::from_secs(2)));
+        assert_eq!(timeouts.operation_timeout(), Some(Duration::from_secs(3600)));
+        assert_eq!(timeouts.read_timeout(), None);
+
+        assert!(NetworkPolicy::default().retry_config().is_none());
+        assert!(NetworkPolicy::default().timeout_config().is_none());
+    }
+
+    #[test]
+    fn test_transport_settings_are_validated_up_front() {
+        let proxy = ProxySettings {
+            url: "http://proxy.corp.example:3128".to_string(),
+            username: Some("svc".to_string()),
+            password: Some("secret".to_string()),
+            no_proxy: Some("localhost,.corp.example,10.0.0.0/8".to_string()),
+        };
+        assert!(Transport::new(Some(30), Some(&proxy), None).is_ok());
+
+        let bad_proxy = ProxySettings { url: "not a url".to_string(), ..Default::default() };
+        assert!(Transport::new(None, Some(&bad_proxy), None).is_err());
+
+        let bad_ca = TlsSettings {
+            ca_certificates_pem: Some("-----BEGIN CERTIFICATE-----\nnope\n-----END CERTIFICATE-----".to_string()),
+            ..Default::default()
+        };
+        assert!(Transport::new(None, None, Some(&bad_ca)).is_err());
+
+        let cert_without_key = TlsSettings {
+            client_certificate_pem: Some("-----BEGIN CERTIFICATE-----".to_string()),
+            ..Default::default()
+        };
+        let error = Transport::new(None, None, Some(&cert_without_key)).err().unwrap();
+        assert_eq!(error.kind, crate::error::ErrorKind::InvalidRequest);
+
+        // Nothing configured keeps the SDK's own client
+        assert!(http_client(&ConnectionOptions::default()).unwrap().is_none());
+    }
+}
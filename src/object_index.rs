@@ -0,0 +1,621 @@
+use aws_sdk_s3::types::Object;
+use aws_sdk_s3::Client as S3Client;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::BucketStackError;
+
+// Global database connection (lazy initialized)
+static INDEX_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+const DEFAULT_LIMIT: usize = 1000;
+const MAX_LIMIT: usize = 10_000;
+// Trigram FTS needs at least this many characters to use the index
+const MIN_INDEXED_QUERY: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    // Anywhere in the full key
+    #[default]
+    Substring,
+    // Whole key, `*` and `?` match `/` too, e.g. "photos/*.jpg"
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SearchOptions {
+    #[serde(default)]
+    pub mode: MatchMode,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    pub prefix: Option<String>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    // RFC 3339
+    pub modified_after: Option<String>,
+    pub modified_before: Option<String>,
+    // Without the dot, any case
+    pub extensions: Option<Vec<String>>,
+    pub storage_classes: Option<Vec<String>>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IndexedObject {
+    pub key: String,
+    pub size: i64,
+    pub last_modified: String,
+    pub storage_class: Option<String>,
+    pub etag: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexStatus {
+    pub endpoint: String,
+    pub bucket: String,
+    pub object_count: i64,
+    pub last_crawl_started: Option<String>,
+    pub last_crawl_completed: Option<String>,
+    // An interrupted crawl resumes from where it stopped
+    pub crawl_in_progress: bool,
+}
+
+fn endpoint_key(endpoint: &str) -> String {
+    endpoint.trim().trim_end_matches('/').to_string()
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339()
+}
+
+pub fn init_object_index_db(app_data_dir: &Path) -> Result<(), String> {
+    let db_path = app_data_dir.join("object_index.db");
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open object index database: {}", e))?;
+
+    conn.execute_batch(
+        "PRAGMA journal_mode = WAL;
+        CREATE TABLE IF NOT EXISTS indexed_buckets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            endpoint TEXT NOT NULL,
+            bucket TEXT NOT NULL,
+            generation INTEGER NOT NULL DEFAULT 0,
+            -- Last key stored by an unfinished crawl
+            crawl_after TEXT,
+            last_crawl_started TEXT,
+            last_crawl_completed TEXT,
+            UNIQUE(endpoint, bucket)
+        );
+        CREATE TABLE IF NOT EXISTS objects (
+            id INTEGER PRIMARY KEY,
+            bucket_id INTEGER NOT NULL,
+            key TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            last_modified TEXT NOT NULL,
+            modified_at INTEGER,
+            extension TEXT,
+            storage_class TEXT,
+            etag TEXT,
+            generation INTEGER NOT NULL,
+            UNIQUE(bucket_id, key)
+        );
+        CREATE INDEX IF NOT EXISTS idx_objects_size ON objects(bucket_id, size);
+        CREATE INDEX IF NOT EXISTS idx_objects_modified ON objects(bucket_id, modified_at);
+        CREATE INDEX IF NOT EXISTS idx_objects_extension ON objects(bucket_id, extension);
+        CREATE VIRTUAL TABLE IF NOT EXISTS object_keys USING fts5(
+            key, content='objects', content_rowid='id', tokenize='trigram'
+        );
+        -- Keys never change in place, so only inserts and deletes touch the FTS index
+        CREATE TRIGGER IF NOT EXISTS objects_ai AFTER INSERT ON objects BEGIN
+            INSERT INTO object_keys(rowid, key) VALUES (new.id, new.key);
+        END;
+        CREATE TRIGGER IF NOT EXISTS objects_ad AFTER DELETE ON objects BEGIN
+            INSERT INTO object_keys(object_keys, rowid, key) VALUES ('delete', old.id, old.key);
+        END;",
+    ).map_err(|e| format!("Failed to create object index tables: {}", e))?;
+
+    let mut db = INDEX_DB.lock().unwrap();
+    *db = Some(conn);
+
+    Ok(())
+}
+
+pub fn close() {
+    let mut db = INDEX_DB.lock().unwrap();
+    *db = None;
+}
+
+fn with_db<T>(f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T, BucketStackError> {
+    let mut db_guard = INDEX_DB.lock().unwrap();
+    let conn = db_guard.as_mut().ok_or("Object index database not initialized")?;
+    f(conn).map_err(|e| BucketStackError::wrap("Object index", e))
+}
+
+fn bucket_id(conn: &Connection, endpoint: &str, bucket: &str) -> rusqlite::Result<Option<(i64, i64)>> {
+    conn.query_row(
+        "SELECT id, generation FROM indexed_buckets WHERE endpoint = ?1 AND bucket = ?2",
+        params![endpoint_key(endpoint), bucket],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+pub fn status(endpoint: &str, bucket: &str) -> Result<Option<IndexStatus>, BucketStackError> {
+    with_db(|conn| {
+        conn.query_row(
+            "SELECT b.crawl_after IS NOT NULL, b.last_crawl_started, b.last_crawl_completed,
+                (SELECT COUNT(*) FROM objects o WHERE o.bucket_id = b.id)
+             FROM indexed_buckets b WHERE b.endpoint = ?1 AND b.bucket = ?2",
+            params![endpoint_key(endpoint), bucket],
+            |row| {
+                Ok(IndexStatus {
+                    endpoint: endpoint.to_string(),
+                    bucket: bucket.to_string(),
+                    crawl_in_progress: row.get(0)?,
+                    last_crawl_started: row.get(1)?,
+                    last_crawl_completed: row.get(2)?,
+                    object_count: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+    })
+}
+
+pub fn is_indexed(endpoint: &str, bucket: &str) -> bool {
+    with_db(|conn| bucket_id(conn, endpoint, bucket)).ok().flatten().is_some()
+}
+
+pub fn drop_index(endpoint: &str, bucket: &str) -> Result<(), BucketStackError> {
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        if let Some((id, _)) = bucket_id(&tx, endpoint, bucket)? {
+            tx.execute("DELETE FROM objects WHERE bucket_id = ?1", params![id])?;
+            tx.execute("DELETE FROM indexed_buckets WHERE id = ?1", params![id])?;
+        }
+        tx.commit()
+    })
+}
+
+pub fn extension(key: &str) -> Option<String> {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => Some(ext.to_lowercase()),
+        _ => None,
+    }
+}
+
+fn modified_at(last_modified: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(last_modified).ok().map(|t| t.timestamp())
+}
+
+fn upsert(tx: &Connection, bucket_id: i64, generation: i64, object: &IndexedObject) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO objects (bucket_id, key, size, last_modified, modified_at, extension, storage_class, etag, generation)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(bucket_id, key) DO UPDATE SET
+            size = excluded.size, last_modified = excluded.last_modified, modified_at = excluded.modified_at,
+            storage_class = excluded.storage_class, etag = excluded.etag, generation = excluded.generation",
+        params![
+            bucket_id,
+            object.key,
+            object.size,
+            object.last_modified,
+            modified_at(&object.last_modified),
+            extension(&object.key),
+            object.storage_class,
+            object.etag,
+            generation,
+        ],
+    )?;
+    Ok(())
+}
+
+// --- Keeping the index fresh after our own changes ---
+// Best effort and a no-op for buckets that were never indexed; the next
+// crawl corrects anything missed.
+
+/// An object we just wrote
+pub fn note_put(endpoint: &str, bucket: &str, key: &str, size: i64, storage_class: Option<&str>) {
+    let object = IndexedObject {
+        key: key.to_string(),
+        size,
+        last_modified: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        storage_class: storage_class.map(str::to_string),
+        etag: None,
+    };
+    let _ = with_db(|conn| match bucket_id(conn, endpoint, bucket)? {
+        Some((id, generation)) => upsert(conn, id, generation, &object),
+        None => Ok(()),
+    });
+}
+
+/// A server-side copy; the destination takes the source's indexed attributes
+pub fn note_copy(endpoint: &str, source_bucket: &str, source_key: &str, bucket: &str, key: &str) {
+    let _ = with_db(|conn| {
+        let Some((id, generation)) = bucket_id(conn, endpoint, bucket)? else {
+            return Ok(());
+        };
+        let source: Option<(i64, Option<String>, Option<String>)> = match bucket_id(conn, endpoint, source_bucket)? {
+            Some((source_id, _)) => conn
+                .query_row(
+                    "SELECT size, storage_class, etag FROM objects WHERE bucket_id = ?1 AND key = ?2",
+                    params![source_id, source_key],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?,
+            None => None,
+        };
+        let Some((size, storage_class, etag)) = source else {
+            return Ok(());
+        };
+        let object = IndexedObject {
+            key: key.to_string(),
+            size,
+            last_modified: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            storage_class,
+            etag,
+        };
+        upsert(conn, id, generation, &object)
+    });
+}
+
+pub fn note_delete(endpoint: &str, bucket: &str, key: &str) {
+    let _ = with_db(|conn| match bucket_id(conn, endpoint, bucket)? {
+        Some((id, _)) => conn
+            .execute("DELETE FROM objects WHERE bucket_id = ?1 AND key = ?2", params![id, key])
+            .map(|_| ()),
+        None => Ok(()),
+    });
+}
+
+pub fn note_delete_prefix(endpoint: &str, bucket: &str, prefix: &str) {
+    let _ = with_db(|conn| match bucket_id(conn, endpoint, bucket)? {
+        Some((id, _)) => conn
+            .execute(
+                "DELETE FROM objects WHERE bucket_id = ?1 AND substr(key, 1, length(?2)) = ?2",
+                params![id, prefix],
+            )
+            .map(|_| ()),
+        None => Ok(()),
+    });
+}
+
+fn to_indexed(object: &Object) -> Option<IndexedObject> {
+    Some(IndexedObject {
+        key: object.key()?.to_string(),
+        size: object.size().unwrap_or(0),
+        last_modified: object.last_modified().map(|d| d.to_string()).unwrap_or_default(),
+        storage_class: object.storage_class().map(|s| s.as_str().to_string()),
+        etag: object.e_tag().map(str::to_string),
+    })
+}
+
+/// List the whole bucket into the index. Pages are committed as they
+/// arrive, so search works while a crawl runs and an interrupted crawl
+/// continues after the last stored key. Objects not seen by a completed
+/// crawl are removed. `full` restarts from the beginning.
+pub async fn crawl(
+    client: &S3Client,
+    endpoint: &str,
+    bucket: &str,
+    full: bool,
+    progress: impl Fn(i64),
+) -> Result<IndexStatus, BucketStackError> {
+    let (id, generation, resume_after) = with_db(|conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO indexed_buckets (endpoint, bucket) VALUES (?1, ?2)",
+            params![endpoint_key(endpoint), bucket],
+        )?;
+        let (id, generation, crawl_after): (i64, i64, Option<String>) = tx.query_row(
+            "SELECT id, generation, crawl_after FROM indexed_buckets WHERE endpoint = ?1 AND bucket = ?2",
+            params![endpoint_key(endpoint), bucket],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let state = match crawl_after {
+            Some(after) if !full => (id, generation, Some(after).filter(|a| !a.is_empty())),
+            _ => {
+                tx.execute(
+                    "UPDATE indexed_buckets SET generation = generation + 1, crawl_after = '', last_crawl_started = ?1 WHERE id = ?2",
+                    params![now(), id],
+                )?;
+                (id, generation + 1, None)
+            }
+        };
+        tx.commit()?;
+        Ok(state)
+    })?;
+
+    let mut indexed: i64 = 0;
+    let mut continuation_token: Option<String> = None;
+    loop {
+        let mut req = client.list_objects_v2().bucket(bucket).max_keys(1000);
+        req = match (&continuation_token, &resume_after) {
+            (Some(token), _) => req.continuation_token(token),
+            (None, Some(after)) => req.start_after(after),
+            (None, None) => req,
+        };
+        let page = req.send().await.map_err(|e| BucketStackError::wrap("Failed to list objects for the index", e))?;
+
+        let objects: Vec<IndexedObject> = page.contents().iter().filter_map(to_indexed).collect();
+        with_db(|conn| {
+            let tx = conn.transaction()?;
+            for object in &objects {
+                upsert(&tx, id, generation, object)?;
+            }
+            if let Some(last) = objects.last() {
+                tx.execute("UPDATE indexed_buckets SET crawl_after = ?1 WHERE id = ?2", params![last.key, id])?;
+            }
+            tx.commit()
+        })?;
+        indexed += objects.len() as i64;
+        progress(indexed);
+
+        if page.is_truncated().unwrap_or(false) {
+            continuation_token = page.next_continuation_token().map(|s| s.to_string());
+        } else {
+            break;
+        }
+    }
+
+    with_db(|conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM objects WHERE bucket_id = ?1 AND generation < ?2",
+            params![id, generation],
+        )?;
+        tx.execute(
+            "UPDATE indexed_buckets SET crawl_after = NULL, last_crawl_completed = ?1 WHERE id = ?2",
+            params![now(), id],
+        )?;
+        tx.commit()
+    })?;
+
+    status(endpoint, bucket)?.ok_or_else(|| "Index disappeared during the crawl".into())
+}
+
+// Longest run of plain characters in a glob, usable as an FTS prefilter.
+// Bracket expressions match one character, so their contents never count.
+fn glob_literal(pattern: &str) -> &str {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !matches!(c, '*' | '?' | '[') {
+            continue;
+        }
+        runs.push(&pattern[start..i]);
+        if c == '[' {
+            // A `]` right after `[` or `[^` is part of the set
+            chars.next_if(|&(_, c)| c == '^');
+            chars.next_if(|&(_, c)| c == ']');
+            for (_, c) in chars.by_ref() {
+                if c == ']' {
+                    break;
+                }
+            }
+        }
+        start = chars.peek().map_or(pattern.len(), |&(j, _)| j);
+    }
+    runs.push(&pattern[start..]);
+    runs.into_iter().max_by_key(|run| run.chars().count()).unwrap_or("")
+}
+
+fn fts_phrase(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn parse_time(value: &str) -> Result<i64, BucketStackError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.timestamp())
+        .map_err(|e| BucketStackError::invalid(format!("Invalid date '{}': {}", value, e)))
+}
+
+/// Search one bucket's index. Substring and glob queries go through the
+/// trigram index; regexes are checked against every key the filters allow.
+pub fn search(
+    endpoint: &str,
+    bucket: &str,
+    query: &str,
+    options: &SearchOptions,
+) -> Result<Vec<IndexedObject>, BucketStackError> {
+    let limit = options.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let regex = match options.mode {
+        MatchMode::Regex if !query.is_empty() => Some(
+            regex::RegexBuilder::new(query)
+                .case_insensitive(!options.case_sensitive)
+                .build()
+                .map_err(|e| BucketStackError::invalid(format!("Invalid regular expression: {}", e)))?,
+        ),
+        _ => None,
+    };
+
+    let mut sql = String::from("SELECT o.key, o.size, o.last_modified, o.storage_class, o.etag FROM objects o");
+    let mut conditions = vec!["o.bucket_id = ?".to_string()];
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    // Trigram matching ignores case; exact-case checks come after
+    let fts_text = match options.mode {
+        MatchMode::Substring => query,
+        MatchMode::Glob => glob_literal(query),
+        MatchMode::Regex => "",
+    };
+    if fts_text.chars().count() >= MIN_INDEXED_QUERY {
+        sql.push_str(" JOIN object_keys f ON f.rowid = o.id");
+        conditions.push("object_keys MATCH ?".to_string());
+        params_vec.push(Box::new(fts_phrase(fts_text)));
+    }
+    match options.mode {
+        MatchMode::Substring if !query.is_empty() => {
+            if options.case_sensitive {
+                conditions.push("instr(o.key, ?) > 0".to_string());
+                params_vec.push(Box::new(query.to_string()));
+            } else {
+                conditions.push("instr(lower(o.key), ?) > 0".to_string());
+                params_vec.push(Box::new(query.to_lowercase()));
+            }
+        }
+        MatchMode::Glob if !query.is_empty() => {
+            if options.case_sensitive {
+                conditions.push("o.key GLOB ?".to_string());
+                params_vec.push(Box::new(query.to_string()));
+            } else {
+                conditions.push("lower(o.key) GLOB ?".to_string());
+                params_vec.push(Box::new(query.to_lowercase()));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(prefix) = options.prefix.as_deref().filter(|p| !p.is_empty()) {
+        conditions.push("substr(o.key, 1, length(?)) = ?".to_string());
+        params_vec.push(Box::new(prefix.to_string()));
+        params_vec.push(Box::new(prefix.to_string()));
+    }
+    if let Some(min) = options.min_size {
+        conditions.push("o.size >= ?".to_string());
+        params_vec.push(Box::new(min));
+    }
+    if let Some(max) = options.max_size {
+        conditions.push("o.size <= ?".to_string());
+        params_vec.push(Box::new(max));
+    }
+    if let Some(after) = options.modified_after.as_deref().filter(|v| !v.is_empty()) {
+        conditions.push("o.modified_at >= ?".to_string());
+        params_vec.push(Box::new(parse_time(after)?));
+    }
+    if let Some(before) = options.modified_before.as_deref().filter(|v| !v.is_empty()) {
+        conditions.push("o.modified_at < ?".to_string());
+        params_vec.push(Box::new(parse_time(before)?));
+    }
+    for (column, values, lowercase) in [
+        ("o.extension", &options.extensions, true),
+        ("o.storage_class", &options.storage_classes, false),
+    ] {
+        let Some(values) = values.as_ref().filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        conditions.push(format!("{} IN ({})", column, vec!["?"; values.len()].join(", ")));
+        for value in values {
+            let value = value.trim().trim_start_matches('.');
+            params_vec.push(Box::new(if lowercase { value.to_lowercase() } else { value.to_string() }));
+        }
+    }
+
+    sql.push_str(&format!(" WHERE {} ORDER BY o.key", conditions.join(" AND ")));
+    if regex.is_none() {
+        sql.push_str(&format!(" LIMIT {}", limit));
+    }
+
+    with_db(|conn| {
+        let Some((id, _)) = bucket_id(conn, endpoint, bucket)? else {
+            return Ok(Vec::new());
+        };
+        params_vec.insert(0, Box::new(id));
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|b| b.as_ref()).collect();
+
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_refs.as_slice())?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            if regex.as_ref().is_some_and(|re| !re.is_match(&key)) {
+                continue;
+            }
+            results.push(IndexedObject {
+                key,
+                size: row.get(1)?,
+                last_modified: row.get(2)?,
+                storage_class: row.get(3)?,
+                etag: row.get(4)?,
+            });
+            if results.len() >= limit {
+                break;
+            }
+        }
+        Ok(results)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_search_modes_and_filters() {
+        let dir = std::env::temp_dir().join(format!("bucketstack-index-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        init_object_index_db(&dir).unwrap();
+
+        let endpoint = "http://minio.test:9000";
+        with_db(|conn| {
+            conn.execute(
+                "INSERT INTO indexed_buckets (endpoint, bucket, generation) VALUES (?1, 'media', 1)",
+                params![endpoint],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+        for (key, size, modified) in [
+            ("photos/2024/Beach.JPG", 4_000_000, "2024-07-01T10:00:00Z"),
+            ("photos/2024/notes.txt", 120, "2024-07-02T10:00:00Z"),
+            ("backups/db-2023.tar.gz", 900_000_000, "2023-12-31T23:00:00Z"),
+        ] {
+            note_put(endpoint, "media", key, size, Some("STANDARD"));
+            with_db(|conn| {
+                conn.execute(
+                    "UPDATE objects SET last_modified = ?1, modified_at = ?2 WHERE key = ?3",
+                    params![modified, modified_at(modified), key],
+                )
+            })
+            .unwrap();
+        }
+        let keys = |query: &str, options: SearchOptions| -> Vec<String> {
+            search(endpoint, "media", query, &options).unwrap().into_iter().map(|o| o.key).collect()
+        };
+
+        assert_eq!(keys("beach", SearchOptions::default()), vec!["photos/2024/Beach.JPG"]);
+        assert_eq!(keys("2024", SearchOptions::default()).len(), 2);
+        assert_eq!(keys("ph", SearchOptions::default()).len(), 2);
+        assert!(keys("beach", SearchOptions { case_sensitive: true, ..Default::default() }).is_empty());
+        assert_eq!(
+            keys("photos/*.jpg", SearchOptions { mode: MatchMode::Glob, ..Default::default() }),
+            vec!["photos/2024/Beach.JPG"]
+        );
+        // The character class is not a literal to prefilter on
+        assert_eq!(glob_literal("db-202[0123456789].tar.gz"), ".tar.gz");
+        assert_eq!(
+            keys("*db-202[0123456789].tar.gz", SearchOptions { mode: MatchMode::Glob, ..Default::default() }),
+            vec!["backups/db-2023.tar.gz"]
+        );
+        assert_eq!(
+            keys(r"db-\d{4}", SearchOptions { mode: MatchMode::Regex, ..Default::default() }),
+            vec!["backups/db-2023.tar.gz"]
+        );
+        assert_eq!(
+            keys("", SearchOptions {
+                min_size: Some(1000),
+                modified_after: Some("2024-01-01T00:00:00Z".to_string()),
+                ..Default::default()
+            }),
+            vec!["photos/2024/Beach.JPG"]
+        );
+        assert_eq!(
+            keys("", SearchOptions { extensions: Some(vec![".TXT".to_string()]), ..Default::default() }),
+            vec!["photos/2024/notes.txt"]
+        );
+
+        note_delete_prefix(endpoint, "media", "photos/");
+        assert_eq!(keys("", SearchOptions::default()), vec!["backups/db-2023.tar.gz"]);
+
+        close();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,208 @@
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+// Constraints for a browser-form (POST Object) upload
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PostPolicyOptions {
+    // Every uploaded key must start with this; the form key is `<prefix>${filename}`
+    pub key_prefix: String,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    // Exact Content-Type the form must send
+    pub content_type: Option<String>,
+    // Or just a prefix such as "image/"
+    pub content_type_prefix: Option<String>,
+    // Respond with 201 + XML instead of a bare 204
+    pub success_action_status: Option<u16>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PresignedPost {
+    pub url: String,
+    // Hidden form fields; they must precede the `file` field in the form
+    pub fields: HashMap<String, String>,
+    // The signed policy conditions, so callers can show what the form must satisfy
+    pub conditions: Vec<Value>,
+    pub expires_at: String,
+}
+
+pub struct SigningCredentials<'a> {
+    pub access_key_id: &'a str,
+    pub secret_access_key: &'a str,
+    pub session_token: Option<&'a str>,
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// SigV4 signing key: HMAC chain over date, region, service and "aws4_request"
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let k_region = hmac(&k_date, region);
+    let k_service = hmac(&k_region, service);
+    hmac(&k_service, "aws4_request")
+}
+
+pub fn presign_post(
+    url: String,
+    region: &str,
+    bucket: &str,
+    credentials: &SigningCredentials,
+    options: &PostPolicyOptions,
+    expires_in: Duration,
+    now: DateTime<Utc>,
+) -> Result<PresignedPost, String> {
+    if options.content_type.is_some() && options.content_type_prefix.is_some() {
+        return Err("Specify either content_type or content_type_prefix, not both".to_string());
+    }
+    if let (Some(min), Some(max)) = (options.min_size, options.max_size) {
+        if min > max {
+            return Err(format!("min_size ({}) is larger than max_size ({})", min, max));
+        }
+    }
+
+    let date = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential = format!("{}/{}/{}/s3/aws4_request", credentials.access_key_id.trim(), date, region);
+    let expires_at = now + expires_in;
+
+    let mut fields = HashMap::new();
+    fields.insert("key".to_string(), format!("{}${{filename}}", options.key_prefix));
+    fields.insert("x-amz-algorithm".to_string(), ALGORITHM.to_string());
+    fields.insert("x-amz-credential".to_string(), credential.clone());
+    fields.insert("x-amz-date".to_string(), amz_date.clone());
+
+    let mut conditions = vec![
+        json!({ "bucket": bucket }),
+        json!(["starts-with", "$key", options.key_prefix]),
+        json!({ "x-amz-algorithm": ALGORITHM }),
+        json!({ "x-amz-credential": credential }),
+        json!({ "x-amz-date": amz_date }),
+    ];
+
+    if let Some(token) = credentials.session_token {
+        fields.insert("x-amz-security-token".to_string(), token.to_string());
+        conditions.push(json!({ "x-amz-security-token": token }));
+    }
+    if options.min_size.is_some() || options.max_size.is_some() {
+        conditions.push(json!([
+            "content-length-range",
+            options.min_size.unwrap_or(0),
+            // S3's single PUT/POST limit is 5 GiB
+            options.max_size.unwrap_or(5 * 1024 * 1024 * 1024),
+        ]));
+    }
+    if let Some(ct) = &options.content_type {
+        fields.insert("Content-Type".to_string(), ct.clone());
+        conditions.push(json!({ "Content-Type": ct }));
+    }
+    if let Some(prefix) = &options.content_type_prefix {
+        conditions.push(json!(["starts-with", "$Content-Type", prefix]));
+    }
+    if let Some(status) = options.success_action_status {
+        fields.insert("success_action_status".to_string(), status.to_string());
+        conditions.push(json!({ "success_action_status": status.to_string() }));
+    }
+
+    let policy = json!({
+        "expiration": expires_at.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        "conditions": conditions,
+    });
+    let policy_b64 = base64::engine::general_purpose::STANDARD.encode(policy.to_string());
+    let key = signing_key(credentials.secret_access_key.trim(), &date, region, "s3");
+    let signature = hex::encode(hmac(&key, &policy_b64));
+
+    fields.insert("policy".to_string(), policy_b64);
+    fields.insert("x-amz-signature".to_string(), signature);
+
+    Ok(PresignedPost {
+        url,
+        fields,
+        conditions: policy["conditions"].as_array().cloned().unwrap_or_default(),
+        expires_at: expires_at.to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoint::{bucket_url, AddressingStyle};
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_signing_key_matches_aws_example() {
+        // Example from the AWS SigV4 documentation
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20150830", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9");
+    }
+
+    #[test]
+    fn test_presign_post_fields_and_conditions() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let creds = SigningCredentials {
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: "secret",
+            session_token: None,
+        };
+        let options = PostPolicyOptions {
+            key_prefix: "partners/acme/".to_string(),
+            max_size: Some(1024),
+            content_type_prefix: Some("image/".to_string()),
+            ..Default::default()
+        };
+
+        let post = presign_post(
+            bucket_url("", "eu-west-1", "uploads", AddressingStyle::Auto),
+            "eu-west-1",
+            "uploads",
+            &creds,
+            &options,
+            Duration::hours(1),
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(post.url, "https://uploads.s3.eu-west-1.amazonaws.com/");
+        assert_eq!(post.fields["key"], "partners/acme/${filename}");
+        assert_eq!(post.fields["x-amz-credential"], "AKIDEXAMPLE/20240301/eu-west-1/s3/aws4_request");
+        assert_eq!(post.fields["x-amz-date"], "20240301T120000Z");
+        assert!(post.conditions.contains(&json!(["content-length-range", 0, 1024])));
+        assert!(post.conditions.contains(&json!(["starts-with", "$Content-Type", "image/"])));
+
+        // The signature must cover exactly the policy we hand out
+        let key = signing_key("secret", "20240301", "eu-west-1", "s3");
+        assert_eq!(post.fields["x-amz-signature"], hex::encode(hmac(&key, &post.fields["policy"])));
+    }
+
+    #[test]
+    fn test_presign_post_rejects_conflicting_options() {
+        let creds = SigningCredentials { access_key_id: "a", secret_access_key: "b", session_token: None };
+        let options = PostPolicyOptions {
+            content_type: Some("image/png".to_string()),
+            content_type_prefix: Some("image/".to_string()),
+            ..Default::default()
+        };
+        let result = presign_post(
+            bucket_url("https://minio.local:9000/", "us-east-1", "b", AddressingStyle::Auto),
+            "us-east-1",
+            "b",
+            &creds,
+            &options,
+            Duration::hours(1),
+            Utc::now(),
+        );
+        assert!(result.is_err());
+    }
+}
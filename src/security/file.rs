@@ -0,0 +1,969 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use directories::ProjectDirs;
+use machine_uid;
+use aes_gcm::aead::rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use super::{CredentialError, RecoveryKey, SecurityManager, StorageBackend, VaultStatus};
+
+// Hardcoded salt to mix with machine ID
+// WARNING: Changing this will invalidate all existing encrypted data on all machines
+const APP_SALT: &str = "bucketstack-secure-storage-v1-salt-8x92m4";
+const CREDENTIALS_FILE: &str = "credentials.enc";
+const SETTINGS_FILE: &str = "vault.json";
+// Previous versions kept next to the live file as credentials.enc.bak.1 (newest) ..
+const BACKUP_COUNT: usize = 3;
+
+// File layout (format 3):
+//   "BSCF" | version u8 | kdf u8 | [salt | m_cost | t_cost | p_cost] | nonce (12) | ciphertext
+// The KDF block is only present for Argon2id, and the whole header is
+// authenticated as AES-GCM associated data. Older files are still read:
+// format 1 is bare nonce + ciphertext under the machine key, format 2 is
+// "BSV2" + Argon2id salt and parameters without a version byte.
+const FILE_MAGIC: &[u8; 4] = b"BSCF";
+const FORMAT_VERSION: u8 = 3;
+const KDF_MACHINE_ID: u8 = 0;
+const KDF_ARGON2ID: u8 = 1;
+const LEGACY_VAULT_MAGIC: &[u8; 4] = b"BSV2";
+const SALT_LEN: usize = 16;
+const KDF_PARAMS_LEN: usize = SALT_LEN + 12;
+// OWASP recommendation for Argon2id: 19 MiB, 2 iterations, 1 lane
+const DEFAULT_KDF: KdfParams = KdfParams { m_cost: 19 * 1024, t_cost: 2, p_cost: 1 };
+// Upper bounds for parameters read from a header or bundle, so a crafted file
+// can't make unlocking allocate 4 GiB or spin for hours. 1 GiB, in KiB.
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+// Argon2id parameters, shared with connection bundles
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub(crate) struct KdfParams {
+    pub(crate) m_cost: u32,
+    pub(crate) t_cost: u32,
+    pub(crate) p_cost: u32,
+}
+
+impl KdfParams {
+    fn check_bounds(self) -> Result<(), String> {
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err("key derivation parameters are out of range".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Argon2id key for `passphrase`; parameters past the bounds above are refused
+/// since they may come from a file someone else wrote
+pub(crate) fn derive_passphrase_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<[u8; 32], String> {
+    kdf.check_bounds()?;
+    let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+        .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum KeyMode {
+    // v1: key derived from the machine ID, always available
+    Machine,
+    // Key derived from the user's master passphrase; absent while locked
+    Passphrase { salt: [u8; SALT_LEN], kdf: KdfParams },
+}
+
+#[derive(Debug)]
+struct ParsedFile<'a> {
+    mode: KeyMode,
+    // Associated data for decryption (empty for legacy formats)
+    header: &'a [u8],
+    // Nonce + ciphertext
+    body: &'a [u8],
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VaultSettings {
+    // Lock a passphrase vault after this many idle minutes (None = never)
+    pub auto_lock_minutes: Option<u64>,
+}
+
+// AES-256-GCM encrypted JSON map in the config directory
+pub struct FileSecurityManager {
+    key: Option<[u8; 32]>, // AES-256 Key, None while a passphrase vault is locked
+    file_path: PathBuf,
+    mode: KeyMode,
+    settings: VaultSettings,
+    last_used: Instant,
+}
+
+impl FileSecurityManager {
+    pub fn new() -> Self {
+        let file_path = Self::get_credentials_path();
+        let mode = Self::detect_mode(&file_path);
+        let key = match mode {
+            KeyMode::Machine => Some(Self::derive_key()),
+            KeyMode::Passphrase { .. } => None,
+        };
+        Self {
+            key,
+            file_path,
+            mode,
+            settings: Self::load_settings(),
+            last_used: Instant::now(),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_key(key: [u8; 32], file_path: PathBuf) -> Self {
+        Self {
+            key: Some(key),
+            file_path,
+            mode: KeyMode::Machine,
+            settings: VaultSettings::default(),
+            last_used: Instant::now(),
+        }
+    }
+
+    fn derive_key() -> [u8; 32] {
+        // Get unique machine ID
+        let machine_id = machine_uid::get().unwrap_or_else(|_| {
+            eprintln!("⚠️ Failed to get machine ID, falling back to static ID (INSECURE if purely relied upon)");
+            "fallback-machine-id-bucketstack".to_string()
+        });
+
+        Self::key_from_machine_id(&machine_id)
+    }
+
+    fn key_from_machine_id(machine_id: &str) -> [u8; 32] {
+        // Mix with application salt
+        let mut hasher = Sha256::new();
+        hasher.update(machine_id.as_bytes());
+        hasher.update(APP_SALT.as_bytes());
+
+        hasher.finalize().into()
+    }
+
+    fn config_dir() -> Option<PathBuf> {
+        ProjectDirs::from("com", "bucketstack", "app").map(|p| p.config_dir().to_path_buf())
+    }
+
+    fn get_credentials_path() -> PathBuf {
+        if let Some(config_dir) = Self::config_dir() {
+            if !config_dir.exists() {
+                let _ = fs::create_dir_all(&config_dir);
+            }
+            config_dir.join(CREDENTIALS_FILE)
+        } else {
+             // Fallback to local execution dir if getting system paths fails
+            PathBuf::from(CREDENTIALS_FILE)
+        }
+    }
+
+    fn settings_path() -> PathBuf {
+        match Self::config_dir() {
+            Some(dir) => dir.join(SETTINGS_FILE),
+            None => PathBuf::from(SETTINGS_FILE),
+        }
+    }
+
+    fn load_settings() -> VaultSettings {
+        fs::read_to_string(Self::settings_path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    // Read the header (if any) to tell a passphrase vault from a machine-key file
+    fn detect_mode(file_path: &Path) -> KeyMode {
+        fs::read(file_path)
+            .ok()
+            .and_then(|content| Self::parse_file(&content).ok().map(|f| f.mode))
+            .unwrap_or(KeyMode::Machine)
+    }
+
+    fn parse_kdf(rest: &[u8]) -> Result<KeyMode, String> {
+        if rest.len() < KDF_PARAMS_LEN {
+            return Err("header is truncated".to_string());
+        }
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&rest[..SALT_LEN]);
+        let param = |i: usize| u32::from_le_bytes(rest[SALT_LEN + i * 4..SALT_LEN + i * 4 + 4].try_into().unwrap());
+        let kdf = KdfParams { m_cost: param(0), t_cost: param(1), p_cost: param(2) };
+        kdf.check_bounds()?;
+        Ok(KeyMode::Passphrase { salt, kdf })
+    }
+
+    // Format version of a file from a newer release, which must be left alone
+    fn newer_format(content: &[u8]) -> Option<u8> {
+        if !content.starts_with(FILE_MAGIC) {
+            return None;
+        }
+        content.get(FILE_MAGIC.len()).copied().filter(|version| *version > FORMAT_VERSION)
+    }
+
+    fn parse_file(content: &[u8]) -> Result<ParsedFile<'_>, String> {
+        if content.starts_with(FILE_MAGIC) {
+            let version = *content.get(FILE_MAGIC.len()).ok_or("header is truncated")?;
+            if version != FORMAT_VERSION {
+                return Err(format!("unsupported file format version {} (this build reads up to {})", version, FORMAT_VERSION));
+            }
+            let kdf_at = FILE_MAGIC.len() + 2;
+            let (mode, header_len) = match content.get(kdf_at - 1) {
+                Some(&KDF_MACHINE_ID) => (KeyMode::Machine, kdf_at),
+                Some(&KDF_ARGON2ID) => (Self::parse_kdf(&content[kdf_at..])?, kdf_at + KDF_PARAMS_LEN),
+                Some(other) => return Err(format!("unknown key derivation {}", other)),
+                None => return Err("header is truncated".to_string()),
+            };
+            return Ok(ParsedFile { mode, header: &content[..header_len], body: &content[header_len..] });
+        }
+
+        if content.starts_with(LEGACY_VAULT_MAGIC) {
+            let mode = Self::parse_kdf(&content[LEGACY_VAULT_MAGIC.len()..]).map_err(|e| format!("vault {}", e))?;
+            let body = &content[LEGACY_VAULT_MAGIC.len() + KDF_PARAMS_LEN..];
+            return Ok(ParsedFile { mode, header: &[], body });
+        }
+
+        Ok(ParsedFile { mode: KeyMode::Machine, header: &[], body: content })
+    }
+
+    fn header_bytes(mode: &KeyMode) -> Vec<u8> {
+        let mut header = FILE_MAGIC.to_vec();
+        header.push(FORMAT_VERSION);
+        match mode {
+            KeyMode::Machine => header.push(KDF_MACHINE_ID),
+            KeyMode::Passphrase { salt, kdf } => {
+                header.push(KDF_ARGON2ID);
+                header.extend_from_slice(salt);
+                for param in [kdf.m_cost, kdf.t_cost, kdf.p_cost] {
+                    header.extend_from_slice(&param.to_le_bytes());
+                }
+            }
+        }
+        header
+    }
+
+    fn unlocked_key(&self) -> Result<[u8; 32], CredentialError> {
+        self.key.ok_or(CredentialError::Locked)
+    }
+
+    // Decrypt file contents with the given key; the error says why it failed
+    fn decrypt_blob(content: &[u8], key: [u8; 32]) -> Result<HashMap<String, String>, String> {
+        let file = Self::parse_file(content)?;
+
+        if file.body.len() < 12 {
+            return Err("file is too short".to_string());
+        }
+
+        // Extract nonce and ciphertext
+        let (nonce_bytes, ciphertext) = file.body.split_at(12);
+        let nonce = Nonce::from_slice(nonce_bytes);
+
+        let cipher = Aes256Gcm::new(&key.into());
+
+        // Decrypt - fails if the machine ID changed or the file is corrupted
+        let plaintext = cipher
+            .decrypt(nonce, Payload { msg: ciphertext, aad: file.header })
+            .map_err(|_| "decryption failed (wrong key, or the machine ID may have changed)".to_string())?;
+
+        let json = String::from_utf8(plaintext).map_err(|_| "invalid UTF-8".to_string())?;
+        serde_json::from_str(&json).map_err(|e| format!("invalid JSON: {}", e))
+    }
+
+    // credentials.enc + suffix, next to the live file
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let name = self
+            .file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| CREDENTIALS_FILE.to_string());
+        self.file_path.with_file_name(format!("{}{}", name, suffix))
+    }
+
+    // Advisory lock held across each load-modify-save, so two BucketStack
+    // instances can't overwrite each other's changes. Released on drop.
+    fn acquire_lock(&self) -> Result<fs::File, String> {
+        if let Some(parent) = self.file_path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                let _ = fs::create_dir_all(parent);
+            }
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sibling(".lock"))
+            .map_err(|e| format!("Failed to open credentials lock file: {}", e))?;
+        file.lock()
+            .map_err(|e| format!("Failed to lock credentials file: {}", e))?;
+        Ok(file)
+    }
+
+    // Move an unreadable file aside (never delete it) so it can be recovered
+    fn quarantine(&self) -> Result<PathBuf, String> {
+        // The random part keeps a second quarantine within the same second
+        // from replacing the first
+        let mut nonce = [0u8; 4];
+        OsRng.fill_bytes(&mut nonce);
+        let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+        let target = self.sibling(&format!(".quarantine-{}-{}", stamp, hex::encode(nonce)));
+        fs::rename(&self.file_path, &target)
+            .map_err(|e| format!("Failed to quarantine credentials file: {}", e))?;
+        Ok(target)
+    }
+
+    // Backups and quarantined copies of this manager's file
+    fn recovery_paths(&self) -> Vec<PathBuf> {
+        let name = match self.file_path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Vec::new(),
+        };
+        let dir = match self.file_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                file_name.starts_with(&format!("{}.bak.", name)) || file_name.starts_with(&format!("{}.quarantine-", name))
+            })
+            .map(|entry| entry.path())
+            .collect()
+    }
+
+    // After a re-key, bring backups and quarantined copies under the new key
+    // so none stays readable with a weaker one. Copies the old key can't open
+    // (another machine ID, an older format) are left for recover_credentials.
+    fn rekey_recovery_files(&self, old_key: Option<[u8; 32]>) {
+        for path in self.recovery_paths() {
+            let Ok(content) = fs::read(&path) else {
+                continue;
+            };
+            let Some(map) = old_key.and_then(|key| Self::decrypt_blob(&content, key).ok()) else {
+                eprintln!("Warning: {} doesn't open with the old key, leaving it as is", path.display());
+                continue;
+            };
+            let written = self
+                .encrypt_map(&map)
+                .map_err(|e| e.to_string())
+                .and_then(|blob| self.write_atomic_to(&path, &blob));
+            if let Err(e) = written {
+                eprintln!("Warning: Failed to re-encrypt {}, leaving it as is: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn backup_path(&self, index: usize) -> PathBuf {
+        self.sibling(&format!(".bak.{}", index))
+    }
+
+    // Shift credentials.enc.bak.N up by one and copy the live file to .bak.1
+    fn rotate_backups(&self) {
+        if !self.file_path.exists() {
+            return;
+        }
+        for index in (1..BACKUP_COUNT).rev() {
+            let from = self.backup_path(index);
+            if from.exists() {
+                let _ = fs::rename(&from, self.backup_path(index + 1));
+            }
+        }
+        match fs::copy(&self.file_path, self.backup_path(1)) {
+            Ok(_) => restrict_permissions(&self.backup_path(1)),
+            Err(e) => eprintln!("Warning: Failed to back up credentials file: {}", e),
+        }
+    }
+
+    // Encrypts the entire credentials map and saves to disk
+    fn save_map(&self, map: &HashMap<String, String>) -> Result<(), CredentialError> {
+        let final_blob = self.encrypt_map(map)?;
+
+        // Ensure parent directory exists
+        if let Some(parent) = self.file_path.parent() {
+            if !parent.exists() {
+                let _ = fs::create_dir_all(parent);
+            }
+        }
+
+        self.rotate_backups();
+        self.write_atomic(&final_blob)?;
+
+        Ok(())
+    }
+
+    // The file contents for `map` under the current key mode
+    fn encrypt_map(&self, map: &HashMap<String, String>) -> Result<Vec<u8>, CredentialError> {
+        let json = serde_json::to_string(map).map_err(|e| e.to_string())?;
+        let plaintext = json.as_bytes();
+
+        let cipher = Aes256Gcm::new(&self.unlocked_key()?.into());
+
+        // Generate a random nonce
+        let mut nonce_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = Nonce::from_slice(&nonce_bytes);
+
+        // Encrypt, authenticating the header so its KDF parameters can't be swapped
+        let header = Self::header_bytes(&self.mode);
+        let ciphertext = cipher.encrypt(nonce, Payload { msg: plaintext, aad: &header })
+            .map_err(|e| format!("Encryption failed: {:?}", e))?;
+
+        // Format: Header + Nonce (12 bytes) + Ciphertext
+        let mut final_blob = Vec::with_capacity(header.len() + nonce_bytes.len() + ciphertext.len());
+        final_blob.extend_from_slice(&header);
+        final_blob.extend_from_slice(&nonce_bytes);
+        final_blob.extend_from_slice(&ciphertext);
+        Ok(final_blob)
+    }
+
+    // Write to a temp file, fsync, then rename over the live file, so a crash
+    // leaves either the old or the new version but never a torn one
+    fn write_atomic(&self, data: &[u8]) -> Result<(), String> {
+        self.write_atomic_to(&self.file_path, data)
+    }
+
+    fn write_atomic_to(&self, target: &Path, data: &[u8]) -> Result<(), String> {
+        let tmp = self.sibling(&format!(".tmp-{}", std::process::id()));
+        let result = (|| {
+            let mut file = fs::File::create(&tmp)?;
+            restrict_permissions(&tmp);
+            file.write_all(data)?;
+            file.sync_all()?;
+            fs::rename(&tmp, target)
+        })();
+
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp);
+            return Err(format!("Failed to write credentials file: {}", e));
+        }
+
+        // Make the rename itself durable
+        #[cfg(unix)]
+        if let Some(parent) = self.file_path.parent() {
+            if let Ok(dir) = fs::File::open(parent) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    }
+
+    // Loads and decrypts the credentials map
+    fn load_map(&mut self) -> Result<HashMap<String, String>, CredentialError> {
+        if !self.file_path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let file_content = match fs::read(&self.file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                // If we can't read the file, just start fresh
+                eprintln!("Warning: Failed to read credentials file, starting fresh: {}", e);
+                return Ok(HashMap::new());
+            }
+        };
+
+        // A newer release's file isn't damaged; quarantining it (and starting
+        // an empty store) would make a downgrade lose every secret
+        if let Some(version) = Self::newer_format(&file_content) {
+            return Err(CredentialError::UnsupportedVersion { version, supported: FORMAT_VERSION });
+        }
+
+        // Another instance may have set or removed the passphrase since we
+        // started; follow the file instead of treating it as unreadable
+        if let Ok(file) = Self::parse_file(&file_content) {
+            if file.mode != self.mode {
+                self.key = match file.mode {
+                    KeyMode::Machine => Some(Self::derive_key()),
+                    KeyMode::Passphrase { .. } => None,
+                };
+                self.mode = file.mode;
+            }
+        }
+        let key = self.unlocked_key()?;
+
+        match Self::decrypt_blob(&file_content, key) {
+            Ok(map) => Ok(map),
+            Err(reason) => {
+                // Keep the file for `recover_credentials` and tell the UI what happened
+                let path = self.quarantine()?;
+                eprintln!("Warning: Failed to load credentials ({}), moved to {}", reason, path.display());
+                Err(CredentialError::Quarantined { path: path.to_string_lossy().to_string(), reason })
+            }
+        }
+    }
+
+    // Every read/write counts as activity for the auto-lock timer
+    fn touch(&mut self) -> Result<(), CredentialError> {
+        self.lock_if_idle();
+        self.unlocked_key()?;
+        self.last_used = Instant::now();
+        Ok(())
+    }
+
+    // Re-encrypt everything under a new key mode (used for enabling, changing
+    // and removing the passphrase)
+    fn rekey(&mut self, mode: KeyMode, key: [u8; 32]) -> Result<(), CredentialError> {
+        let _lock = self.acquire_lock()?;
+        let map = self.load_map()?;
+        let old_mode = std::mem::replace(&mut self.mode, mode);
+        let old_key = self.key.replace(key);
+        if let Err(e) = self.save_map(&map) {
+            self.mode = old_mode;
+            self.key = old_key;
+            return Err(e);
+        }
+        self.rekey_recovery_files(old_key);
+        self.last_used = Instant::now();
+        Ok(())
+    }
+}
+
+fn restrict_permissions(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecoveryFile {
+    pub path: String,
+    // "quarantine" or "backup"
+    pub kind: String,
+    pub modified: Option<String>,
+    pub size: u64,
+    // Needs the passphrase that was set when it was written
+    pub passphrase_protected: bool,
+}
+
+/// Quarantined files and rotating backups, newest first
+pub fn recovery_files() -> Vec<RecoveryFile> {
+    let credentials_path = FileSecurityManager::get_credentials_path();
+    let dir = match credentials_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut files: Vec<RecoveryFile> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let kind = if name.starts_with(&format!("{}.quarantine-", CREDENTIALS_FILE)) {
+                "quarantine"
+            } else if name.starts_with(&format!("{}.bak.", CREDENTIALS_FILE)) {
+                "backup"
+            } else {
+                return None;
+            };
+            let metadata = entry.metadata().ok()?;
+            let head = fs::read(entry.path()).ok()?;
+            Some(RecoveryFile {
+                path: entry.path().to_string_lossy().to_string(),
+                kind: kind.to_string(),
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+                size: metadata.len(),
+                passphrase_protected: matches!(
+                    FileSecurityManager::parse_file(&head).map(|f| f.mode),
+                    Ok(KeyMode::Passphrase { .. })
+                ),
+            })
+        })
+        .collect();
+
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    files
+}
+
+/// Decrypt a quarantined or backup file with a key other than the current one
+pub fn decrypt_file(path: &Path, key: &RecoveryKey) -> Result<HashMap<String, String>, CredentialError> {
+    let content = fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let file = FileSecurityManager::parse_file(&content)
+        .map_err(|reason| format!("Failed to read {}: {}", path.display(), reason))?;
+
+    if let KeyMode::Passphrase { salt, kdf } = file.mode {
+        let passphrase = match key {
+            RecoveryKey::Passphrase { passphrase } => passphrase,
+            _ => return Err("This file is protected by a passphrase".to_string().into()),
+        };
+        let key = derive_passphrase_key(passphrase, &salt, kdf)?;
+        return FileSecurityManager::decrypt_blob(&content, key)
+            .map_err(|_| CredentialError::IncorrectPassphrase);
+    }
+
+    let key = match key {
+        RecoveryKey::CurrentMachine => FileSecurityManager::derive_key(),
+        RecoveryKey::MachineId { machine_id } => FileSecurityManager::key_from_machine_id(machine_id.trim()),
+        RecoveryKey::RawKey { key_hex } => hex::decode(key_hex.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| "Key must be 64 hex characters".to_string())?,
+        RecoveryKey::Passphrase { .. } => return Err("This file is not passphrase protected".to_string().into()),
+    };
+    FileSecurityManager::decrypt_blob(&content, key)
+        .map_err(|reason| format!("Failed to decrypt {}: {}", path.display(), reason).into())
+}
+
+impl SecurityManager for FileSecurityManager {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::File
+    }
+
+    fn set_item(&mut self, key: String, value: String) -> Result<(), CredentialError> {
+        self.touch()?;
+        let _lock = self.acquire_lock()?;
+        // A quarantine is reported rather than papered over with a new file;
+        // the file has been moved aside, so saving again starts a new one
+        let mut map = self.load_map()?;
+        map.insert(key, value);
+        self.save_map(&map)
+    }
+
+    fn get_item(&mut self, key: &str) -> Result<Option<String>, CredentialError> {
+        self.touch()?;
+        let _lock = self.acquire_lock()?;
+        let map = self.load_map()?;
+        Ok(map.get(key).cloned())
+    }
+
+    fn remove_item(&mut self, key: &str) -> Result<(), CredentialError> {
+        self.touch()?;
+        let _lock = self.acquire_lock()?;
+        let mut map = self.load_map()?;
+        if map.contains_key(key) {
+            map.remove(key);
+            self.save_map(&map)?;
+        }
+        Ok(())
+    }
+
+    fn keys(&mut self) -> Result<Vec<String>, CredentialError> {
+        self.touch()?;
+        let _lock = self.acquire_lock()?;
+        Ok(self.load_map()?.into_keys().collect())
+    }
+
+    fn status(&self) -> VaultStatus {
+        VaultStatus {
+            backend: StorageBackend::File,
+            mode: match self.mode {
+                KeyMode::Machine => "machine".to_string(),
+                KeyMode::Passphrase { .. } => "passphrase".to_string(),
+            },
+            locked: self.key.is_none(),
+            auto_lock_minutes: self.settings.auto_lock_minutes,
+        }
+    }
+
+    fn unlock(&mut self, passphrase: &str) -> Result<(), CredentialError> {
+        let content = fs::read(&self.file_path)
+            .map_err(|e| format!("Failed to read credentials file: {}", e))?;
+        if let Some(version) = Self::newer_format(&content) {
+            return Err(CredentialError::UnsupportedVersion { version, supported: FORMAT_VERSION });
+        }
+        // Take the salt from the file itself in case another instance re-keyed it
+        let mode = Self::parse_file(&content)
+            .map_err(|reason| format!("Failed to read credentials file: {}", reason))?
+            .mode;
+        let (salt, kdf) = match &mode {
+            KeyMode::Passphrase { salt, kdf } => (*salt, *kdf),
+            KeyMode::Machine => return Err("Credential vault is not passphrase protected".to_string().into()),
+        };
+
+        let key = derive_passphrase_key(passphrase, &salt, kdf)?;
+        // Verify by decrypting; a wrong passphrase must never touch the file
+        Self::decrypt_blob(&content, key).map_err(|_| CredentialError::IncorrectPassphrase)?;
+
+        self.mode = mode;
+        self.key = Some(key);
+        self.last_used = Instant::now();
+        Ok(())
+    }
+
+    fn lock(&mut self) {
+        if matches!(self.mode, KeyMode::Passphrase { .. }) {
+            if let Some(mut key) = self.key.take() {
+                key.zeroize();
+            }
+        }
+    }
+
+    /// Lock a passphrase vault that has been idle for longer than the
+    /// configured timeout. Returns true if this call locked it.
+    fn lock_if_idle(&mut self) -> bool {
+        let timeout = match self.settings.auto_lock_minutes {
+            Some(minutes) if minutes > 0 => Duration::from_secs(minutes * 60),
+            _ => return false,
+        };
+        if self.key.is_some()
+            && matches!(self.mode, KeyMode::Passphrase { .. })
+            && self.last_used.elapsed() >= timeout
+        {
+            self.lock();
+            return true;
+        }
+        false
+    }
+
+    /// Protect the vault with a master passphrase. Migrates a v1 machine-key
+    /// file in place; the machine key is not used afterwards.
+    fn set_passphrase(&mut self, current: Option<&str>, new_passphrase: &str) -> Result<(), CredentialError> {
+        if new_passphrase.chars().count() < 8 {
+            return Err("Passphrase must be at least 8 characters".to_string().into());
+        }
+        if let KeyMode::Passphrase { .. } = self.mode {
+            // Changing the passphrase requires proving the old one
+            self.unlock(current.ok_or_else(|| "Current passphrase is required".to_string())?)?;
+        }
+        self.unlocked_key()?;
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key = derive_passphrase_key(new_passphrase, &salt, DEFAULT_KDF)?;
+        self.rekey(KeyMode::Passphrase { salt, kdf: DEFAULT_KDF }, key)
+    }
+
+    /// Go back to the machine-bound key
+    fn remove_passphrase(&mut self, passphrase: &str) -> Result<(), CredentialError> {
+        self.unlock(passphrase)?;
+        self.rekey(KeyMode::Machine, Self::derive_key())
+    }
+
+    fn set_auto_lock(&mut self, minutes: Option<u64>) -> Result<(), CredentialError> {
+        let settings = VaultSettings { auto_lock_minutes: minutes.filter(|m| *m > 0) };
+        let path = Self::settings_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write vault settings: {}", e))?;
+        self.settings = settings;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bucketstack_test_{}_{}", name, std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos()))
+    }
+
+    #[test]
+    fn test_save_creates_directory_if_missing() {
+        // Create a unique temp path
+        let temp_dir = temp_path("dir");
+        let file_path = temp_dir.join("creds.enc");
+
+        // Ensure clean state: remove parent directory if it happens to exist
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir).unwrap();
+        }
+        assert!(!temp_dir.exists());
+
+        // Create manager manually with the test path
+        // We can access private fields since we are in a child module of the file
+        let key = FileSecurityManager::derive_key();
+        let manager = FileSecurityManager::with_key(key, file_path.clone());
+
+        // Save dummy data
+        let mut map = HashMap::new();
+        map.insert("test".to_string(), "value".to_string());
+
+        // This should succeed (would fail before fix)
+        let result = manager.save_map(&map);
+        assert!(result.is_ok(), "save_map failed: {:?}", result.err());
+
+        // Verify directory and file exist
+        assert!(temp_dir.exists());
+        assert!(file_path.exists());
+
+        // Cleanup
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_passphrase_migration_lock_and_unlock() {
+        let temp_dir = temp_path("vault");
+        let file_path = temp_dir.join("creds.enc");
+
+        // Start from a v1 machine-key file
+        let mut manager = FileSecurityManager::with_key(FileSecurityManager::derive_key(), file_path.clone());
+        manager.set_item("bucketstack-1-secret".to_string(), "s3cr3t".to_string()).unwrap();
+
+        manager.set_passphrase(None, "correct horse battery").unwrap();
+        assert_eq!(&fs::read(&file_path).unwrap()[..4], FILE_MAGIC);
+        assert_eq!(FileSecurityManager::detect_mode(&file_path), manager.mode);
+
+        manager.lock();
+        assert!(manager.get_item("bucketstack-1-secret").is_err());
+        assert!(manager.unlock("wrong passphrase").is_err());
+        // A wrong passphrase must leave the vault intact
+        assert!(file_path.exists());
+
+        manager.unlock("correct horse battery").unwrap();
+        assert_eq!(manager.get_item("bucketstack-1-secret").unwrap().as_deref(), Some("s3cr3t"));
+
+        manager.remove_passphrase("correct horse battery").unwrap();
+        assert_eq!(FileSecurityManager::detect_mode(&file_path), KeyMode::Machine);
+        assert_eq!(manager.get_item("bucketstack-1-secret").unwrap().as_deref(), Some("s3cr3t"));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_setting_a_passphrase_rekeys_backups() {
+        let temp_dir = temp_path("rekey_backups");
+        let file_path = temp_dir.join(CREDENTIALS_FILE);
+        let machine_key = FileSecurityManager::key_from_machine_id("board");
+
+        let mut manager = FileSecurityManager::with_key(machine_key, file_path.clone());
+        manager.set_item("bucketstack-1-secret".to_string(), "s3cr3t".to_string()).unwrap();
+        manager.set_item("bucketstack-2-secret".to_string(), "other".to_string()).unwrap();
+        // A leftover machine-key copy from another machine
+        fs::write(manager.sibling(".quarantine-20240101T000000Z"), fs::read(&file_path).unwrap()).unwrap();
+        let foreign = FileSecurityManager::with_key(FileSecurityManager::key_from_machine_id("old-board"), temp_dir.join("other.enc"));
+        let mut map = HashMap::new();
+        map.insert("bucketstack-3-secret".to_string(), "old".to_string());
+        fs::write(manager.sibling(".bak.3"), foreign.encrypt_map(&map).unwrap()).unwrap();
+
+        manager.set_passphrase(None, "correct horse battery").unwrap();
+
+        // The foreign copy can't be re-keyed but stays recoverable with its own key
+        let foreign_copy = fs::read(manager.backup_path(3)).unwrap();
+        assert!(FileSecurityManager::decrypt_blob(&foreign_copy, FileSecurityManager::key_from_machine_id("old-board")).is_ok());
+
+        let files: Vec<PathBuf> = manager.recovery_paths().into_iter().filter(|p| *p != manager.backup_path(3)).collect();
+        assert!(!files.is_empty());
+        for path in files {
+            let content = fs::read(&path).unwrap();
+            assert!(FileSecurityManager::decrypt_blob(&content, machine_key).is_err(), "{} still opens with the machine key", path.display());
+            assert!(matches!(FileSecurityManager::parse_file(&content).unwrap().mode, KeyMode::Passphrase { .. }));
+            let map = decrypt_file(&path, &RecoveryKey::Passphrase { passphrase: "correct horse battery".to_string() }).unwrap();
+            assert_eq!(map.get("bucketstack-1-secret").map(String::as_str), Some("s3cr3t"));
+        }
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_unreadable_file_is_quarantined_and_recoverable() {
+        let temp_dir = temp_path("quarantine");
+        let file_path = temp_dir.join(CREDENTIALS_FILE);
+
+        let mut old = FileSecurityManager::with_key(FileSecurityManager::key_from_machine_id("old-board"), file_path.clone());
+        old.set_item("bucketstack-1-secret".to_string(), "s3cr3t".to_string()).unwrap();
+        old.set_item("bucketstack-2-secret".to_string(), "other".to_string()).unwrap();
+        // The second save backed up the first version
+        assert!(old.backup_path(1).exists());
+
+        // Same file, different machine ID (motherboard swap)
+        let mut new = FileSecurityManager::with_key(FileSecurityManager::key_from_machine_id("new-board"), file_path.clone());
+        let quarantined = match new.set_item("bucketstack-3-secret".to_string(), "new".to_string()) {
+            Err(CredentialError::Quarantined { path, .. }) => PathBuf::from(path),
+            other => panic!("expected quarantine, got {:?}", other),
+        };
+        assert!(!file_path.exists());
+        assert!(quarantined.exists());
+
+        // A second quarantine in the same second keeps the first
+        fs::copy(&quarantined, &file_path).unwrap();
+        let second = match new.get_item("bucketstack-1-secret") {
+            Err(CredentialError::Quarantined { path, .. }) => PathBuf::from(path),
+            other => panic!("expected quarantine, got {:?}", other),
+        };
+        assert_ne!(second, quarantined);
+        assert!(quarantined.exists() && second.exists());
+
+        let wrong = decrypt_file(&quarantined, &RecoveryKey::MachineId { machine_id: "new-board".to_string() });
+        assert!(wrong.is_err());
+        let map = decrypt_file(&quarantined, &RecoveryKey::MachineId { machine_id: "old-board".to_string() }).unwrap();
+        assert_eq!(map.get("bucketstack-1-secret").map(String::as_str), Some("s3cr3t"));
+        assert_eq!(map.len(), 2);
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+
+    #[test]
+    fn test_file_format_header_permissions_and_legacy_read() {
+        let temp_dir = temp_path("format");
+        let file_path = temp_dir.join(CREDENTIALS_FILE);
+        let key = FileSecurityManager::key_from_machine_id("board");
+
+        // A v1 file is bare nonce + ciphertext without associated data
+        let nonce = [7u8; 12];
+        let ciphertext = Aes256Gcm::new(&key.into())
+            .encrypt(Nonce::from_slice(&nonce), br#"{"bucketstack-1-secret":"legacy"}"#.as_ref())
+            .unwrap();
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(&file_path, [nonce.as_slice(), &ciphertext].concat()).unwrap();
+
+        let mut manager = FileSecurityManager::with_key(key, file_path.clone());
+        assert_eq!(manager.get_item("bucketstack-1-secret").unwrap().as_deref(), Some("legacy"));
+
+        // The next write upgrades it to the versioned format
+        manager.set_item("bucketstack-2-secret".to_string(), "new".to_string()).unwrap();
+        let content = fs::read(&file_path).unwrap();
+        assert_eq!(&content[..4], FILE_MAGIC);
+        assert_eq!(content[4], FORMAT_VERSION);
+        assert_eq!(content[5], KDF_MACHINE_ID);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&file_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        // No temp file is left behind
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().filter(|e| e.as_ref().unwrap().file_name().to_string_lossy().contains(".tmp-")).count(), 0);
+
+        // Tampering with the authenticated header breaks decryption
+        let mut tampered = content.clone();
+        tampered[5] = KDF_ARGON2ID;
+        assert!(FileSecurityManager::decrypt_blob(&tampered, key).is_err());
+
+        // Files from a newer release are refused rather than misread, and
+        // left in place instead of being quarantined
+        let mut newer = content;
+        newer[4] = FORMAT_VERSION + 1;
+        assert!(FileSecurityManager::parse_file(&newer).unwrap_err().contains("unsupported file format version"));
+        fs::write(&file_path, &newer).unwrap();
+        for result in [manager.get_item("bucketstack-1-secret").map(|_| ()), manager.set_item("k".to_string(), "v".to_string())] {
+            assert!(matches!(result, Err(CredentialError::UnsupportedVersion { version, .. }) if version == FORMAT_VERSION + 1));
+        }
+        assert_eq!(fs::read(&file_path).unwrap(), newer);
+        assert!(manager.recovery_paths().iter().all(|p| !p.to_string_lossy().contains(".quarantine-")));
+
+        // Crafted Argon2 parameters are rejected before any key derivation
+        let mut header = FileSecurityManager::header_bytes(&KeyMode::Passphrase { salt: [0; SALT_LEN], kdf: DEFAULT_KDF });
+        let m_cost_at = FILE_MAGIC.len() + 2 + SALT_LEN;
+        header[m_cost_at..m_cost_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        header.extend_from_slice(&[0u8; 40]);
+        assert!(FileSecurityManager::parse_file(&header).unwrap_err().contains("out of range"));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+    }
+}
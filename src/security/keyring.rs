@@ -0,0 +1,117 @@
+use secret_service::blocking::{Collection, SecretService};
+use secret_service::EncryptionType;
+use std::collections::HashMap;
+
+use super::{CredentialError, SecurityManager, StorageBackend};
+
+// Every item we create carries these attributes so we can find (only) ours
+const APP_ATTRIBUTE: &str = "application";
+const APP_NAME: &str = "bucketstack";
+const KEY_ATTRIBUTE: &str = "bucketstack-key";
+
+/// Secrets stored in the user's login keyring via the freedesktop Secret
+/// Service (GNOME Keyring, KWallet). The keyring daemon owns encryption and
+/// unlocking, so nothing is written to files we manage.
+pub struct KeyringSecurityManager;
+
+impl KeyringSecurityManager {
+    /// Fails if no Secret Service provider is running on the session bus
+    pub fn connect() -> Result<Self, String> {
+        Self::with_collection(|_| Ok(()))?;
+        Ok(Self)
+    }
+
+    // The blocking client borrows from the service handle, so a connection is
+    // opened per operation; secrets are read rarely enough for this to be fine
+    fn with_collection<T>(f: impl FnOnce(&Collection) -> Result<T, String>) -> Result<T, String> {
+        let service = SecretService::connect(EncryptionType::Dh)
+            .map_err(|e| format!("Failed to connect to the Secret Service: {}", e))?;
+        let collection = service
+            .get_default_collection()
+            .map_err(|e| format!("Failed to open the default keyring: {}", e))?;
+        // Prompts the user if the keyring is locked (e.g. after screen lock)
+        collection
+            .ensure_unlocked()
+            .map_err(|e| format!("Failed to unlock the keyring: {}", e))?;
+        f(&collection)
+    }
+
+    fn attributes(key: &str) -> HashMap<&str, &str> {
+        HashMap::from([(APP_ATTRIBUTE, APP_NAME), (KEY_ATTRIBUTE, key)])
+    }
+}
+
+impl SecurityManager for KeyringSecurityManager {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Keyring
+    }
+
+    fn set_item(&mut self, key: String, value: String) -> Result<(), CredentialError> {
+        Self::with_collection(|collection| {
+            collection
+                .create_item(
+                    &format!("BucketStack: {}", key),
+                    Self::attributes(&key),
+                    value.as_bytes(),
+                    true, // replace an existing item with the same attributes
+                    "text/plain",
+                )
+                .map(|_| ())
+                .map_err(|e| format!("Failed to store secret in keyring: {}", e))
+        })
+        .map_err(CredentialError::from)
+    }
+
+    fn get_item(&mut self, key: &str) -> Result<Option<String>, CredentialError> {
+        Self::with_collection(|collection| {
+            let items = collection
+                .search_items(Self::attributes(key))
+                .map_err(|e| format!("Failed to search keyring: {}", e))?;
+            match items.first() {
+                Some(item) => {
+                    let secret = item
+                        .get_secret()
+                        .map_err(|e| format!("Failed to read secret from keyring: {}", e))?;
+                    String::from_utf8(secret)
+                        .map(Some)
+                        .map_err(|_| format!("Keyring secret for {} is not valid UTF-8", key))
+                }
+                None => Ok(None),
+            }
+        })
+        .map_err(CredentialError::from)
+    }
+
+    fn remove_item(&mut self, key: &str) -> Result<(), CredentialError> {
+        Self::with_collection(|collection| {
+            let items = collection
+                .search_items(Self::attributes(key))
+                .map_err(|e| format!("Failed to search keyring: {}", e))?;
+            for item in items {
+                item.delete()
+                    .map_err(|e| format!("Failed to delete secret from keyring: {}", e))?;
+            }
+            Ok(())
+        })
+        .map_err(CredentialError::from)
+    }
+
+    fn keys(&mut self) -> Result<Vec<String>, CredentialError> {
+        Self::with_collection(|collection| {
+            let items = collection
+                .search_items(HashMap::from([(APP_ATTRIBUTE, APP_NAME)]))
+                .map_err(|e| format!("Failed to search keyring: {}", e))?;
+            let mut keys = Vec::new();
+            for item in items {
+                let attributes = item
+                    .get_attributes()
+                    .map_err(|e| format!("Failed to read keyring item: {}", e))?;
+                if let Some(key) = attributes.get(KEY_ATTRIBUTE) {
+                    keys.push(key.clone());
+                }
+            }
+            Ok(keys)
+        })
+        .map_err(CredentialError::from)
+    }
+}
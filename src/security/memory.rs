@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use super::{CredentialError, SecurityManager, StorageBackend};
+
+// In-memory stand-in for tests; `fail_writes` simulates a backend that
+// rejects writes (full disk, locked keyring)
+#[derive(Default)]
+pub struct MemorySecurityManager {
+    pub items: HashMap<String, String>,
+    pub fail_writes: bool,
+}
+
+impl SecurityManager for MemorySecurityManager {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::File
+    }
+
+    fn set_item(&mut self, key: String, value: String) -> Result<(), CredentialError> {
+        if self.fail_writes {
+            return Err("Write rejected".to_string().into());
+        }
+        self.items.insert(key, value);
+        Ok(())
+    }
+
+    fn get_item(&mut self, key: &str) -> Result<Option<String>, CredentialError> {
+        Ok(self.items.get(key).cloned())
+    }
+
+    fn remove_item(&mut self, key: &str) -> Result<(), CredentialError> {
+        self.items.remove(key);
+        Ok(())
+    }
+
+    fn keys(&mut self) -> Result<Vec<String>, CredentialError> {
+        Ok(self.items.keys().cloned().collect())
+    }
+}
@@ -0,0 +1,292 @@
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Global database connection (lazy initialized)
+static SHARE_LINK_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShareLink {
+    pub id: Option<i64>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub connection_id: String,
+    pub bucket: String,
+    pub object_key: String,
+    pub version_id: Option<String>,
+    // How the link was signed. The URL itself is a bearer credential until it
+    // expires, so it is never stored.
+    pub disposition: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    // Access key the URL was signed with; the link dies with that key
+    pub access_key_id: String,
+    pub created_by: Option<String>,
+    pub note: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevokeResult {
+    pub id: i64,
+    // The URL itself no longer works (as opposed to only being marked revoked here)
+    pub invalidated: bool,
+    // Other unexpired links signed with the same access key
+    pub links_sharing_key: i64,
+    pub message: String,
+}
+
+// Fixed-width UTC timestamps so string comparison in SQL is chronological
+pub fn timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn init_share_link_db(app_data_dir: &Path) -> Result<(), String> {
+    let db_path = app_data_dir.join("share_links.db");
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open share link database: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS share_links (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            connection_id TEXT NOT NULL,
+            bucket TEXT NOT NULL,
+            object_key TEXT NOT NULL,
+            version_id TEXT,
+            disposition TEXT,
+            filename TEXT,
+            content_type TEXT,
+            access_key_id TEXT NOT NULL,
+            created_by TEXT,
+            note TEXT,
+            revoked_at TEXT
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create share_links table: {}", e))?;
+
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_share_expires ON share_links(expires_at)",
+        [],
+    );
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_share_connection ON share_links(connection_id)",
+        [],
+    );
+
+    let mut db = SHARE_LINK_DB.lock().unwrap();
+    *db = Some(conn);
+
+    Ok(())
+}
+
+pub fn close() {
+    let mut db = SHARE_LINK_DB.lock().unwrap();
+    *db = None;
+}
+
+pub fn record(link: &ShareLink) -> Result<i64, String> {
+    let db_guard = SHARE_LINK_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Share link database not initialized")?;
+
+    conn.execute(
+        "INSERT INTO share_links (
+            created_at, expires_at, connection_id, bucket, object_key, version_id,
+            disposition, filename, content_type, access_key_id, created_by, note
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            link.created_at,
+            link.expires_at,
+            link.connection_id,
+            link.bucket,
+            link.object_key,
+            link.version_id,
+            link.disposition,
+            link.filename,
+            link.content_type,
+            link.access_key_id,
+            link.created_by,
+            link.note,
+        ],
+    ).map_err(|e| format!("Failed to record share link: {}", e))?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+fn query(sql: &str, params_vec: Vec<Box<dyn rusqlite::ToSql>>) -> Result<Vec<ShareLink>, String> {
+    let db_guard = SHARE_LINK_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Share link database not initialized")?;
+
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|b| b.as_ref()).collect();
+    let mut stmt = conn.prepare(sql)
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let links = stmt.query_map(params_refs.as_slice(), |row| {
+        Ok(ShareLink {
+            id: row.get(0)?,
+            created_at: row.get(1)?,
+            expires_at: row.get(2)?,
+            connection_id: row.get(3)?,
+            bucket: row.get(4)?,
+            object_key: row.get(5)?,
+            version_id: row.get(6)?,
+            disposition: row.get(7)?,
+            filename: row.get(8)?,
+            content_type: row.get(9)?,
+            access_key_id: row.get(10)?,
+            created_by: row.get(11)?,
+            note: row.get(12)?,
+            revoked_at: row.get(13)?,
+        })
+    })
+    .map_err(|e| format!("Failed to query: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Failed to collect results: {}", e))?;
+
+    Ok(links)
+}
+
+const SELECT_COLUMNS: &str = "SELECT id, created_at, expires_at, connection_id, bucket, object_key, version_id, disposition, filename, content_type, access_key_id, created_by, note, revoked_at FROM share_links";
+
+// All links, newest first; expired and revoked links only when asked for
+pub fn list(connection_id: Option<String>, bucket: Option<String>, include_inactive: bool) -> Result<Vec<ShareLink>, String> {
+    let mut sql = format!("{} WHERE 1=1", SELECT_COLUMNS);
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(conn_id) = connection_id {
+        sql.push_str(" AND connection_id = ?");
+        params_vec.push(Box::new(conn_id));
+    }
+    if let Some(bucket) = bucket {
+        sql.push_str(" AND bucket = ?");
+        params_vec.push(Box::new(bucket));
+    }
+    if !include_inactive {
+        sql.push_str(" AND revoked_at IS NULL AND expires_at > ?");
+        params_vec.push(Box::new(timestamp(Utc::now())));
+    }
+    sql.push_str(" ORDER BY created_at DESC");
+
+    query(&sql, params_vec)
+}
+
+// Active links that expire within the given window, soonest first
+pub fn expiring_within(seconds: i64) -> Result<Vec<ShareLink>, String> {
+    let now = Utc::now();
+    let sql = format!(
+        "{} WHERE revoked_at IS NULL AND expires_at > ? AND expires_at <= ? ORDER BY expires_at ASC",
+        SELECT_COLUMNS
+    );
+    query(&sql, vec![
+        Box::new(timestamp(now)),
+        Box::new(timestamp(now + Duration::seconds(seconds))),
+    ])
+}
+
+/// Revocation is advisory. A presigned URL can't be recalled: it stays valid
+/// until it expires or the access key that signed it is deactivated. This
+/// marks the link revoked (hiding it from active lists) and reports, in
+/// `invalidated` and `links_sharing_key`, whether it still works and how many
+/// other live links would die if that key were rotated.
+pub fn revoke(id: i64) -> Result<RevokeResult, String> {
+    let db_guard = SHARE_LINK_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Share link database not initialized")?;
+    let now = timestamp(Utc::now());
+
+    let (access_key_id, expires_at): (String, String) = conn
+        .query_row(
+            "SELECT access_key_id, expires_at FROM share_links WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Share link {} not found: {}", id, e))?;
+
+    conn.execute(
+        "UPDATE share_links SET revoked_at = ?1 WHERE id = ?2 AND revoked_at IS NULL",
+        params![now, id],
+    ).map_err(|e| format!("Failed to revoke share link: {}", e))?;
+
+    if expires_at <= now {
+        return Ok(RevokeResult {
+            id,
+            invalidated: true,
+            links_sharing_key: 0,
+            message: "Link had already expired".to_string(),
+        });
+    }
+
+    let links_sharing_key: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM share_links WHERE access_key_id = ?1 AND id != ?2 AND revoked_at IS NULL AND expires_at > ?3",
+            params![access_key_id, id, now],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count share links: {}", e))?;
+
+    Ok(RevokeResult {
+        id,
+        invalidated: false,
+        links_sharing_key,
+        message: format!(
+            "Link marked as revoked, but it stays usable until {} unless access key {} is rotated or deactivated",
+            expires_at, access_key_id
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(key: &str, access_key_id: &str, expires_in: Duration) -> ShareLink {
+        let now = Utc::now();
+        ShareLink {
+            id: None,
+            created_at: timestamp(now),
+            expires_at: timestamp(now + expires_in),
+            connection_id: "conn".to_string(),
+            bucket: "b".to_string(),
+            object_key: key.to_string(),
+            version_id: None,
+            disposition: Some("inline".to_string()),
+            filename: None,
+            content_type: None,
+            access_key_id: access_key_id.to_string(),
+            created_by: None,
+            note: None,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_list_expiring_and_advisory_revoke() {
+        let dir = std::env::temp_dir().join(format!("bucketstack-share-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        init_share_link_db(&dir).unwrap();
+
+        let soon = record(&link("soon.pdf", "AKIA1", Duration::minutes(30))).unwrap();
+        let later = record(&link("later.pdf", "AKIA1", Duration::days(3))).unwrap();
+        record(&link("expired.pdf", "AKIA2", Duration::minutes(-5))).unwrap();
+
+        let keys = |links: Vec<ShareLink>| links.into_iter().map(|l| l.object_key).collect::<Vec<_>>();
+        assert_eq!(keys(list(None, Some("b".to_string()), false).unwrap()).len(), 2);
+        assert_eq!(keys(list(None, None, true).unwrap()).len(), 3);
+        assert_eq!(keys(expiring_within(3600).unwrap()), ["soon.pdf"]);
+
+        // The URL keeps working; the result says so and names the key to rotate
+        let revoked = revoke(soon).unwrap();
+        assert!(!revoked.invalidated);
+        assert_eq!(revoked.links_sharing_key, 1);
+        assert_eq!(keys(list(None, None, false).unwrap()), ["later.pdf"]);
+        assert!(expiring_within(3600).unwrap().is_empty());
+        assert!(list(None, None, true).unwrap().iter().any(|l| l.id == Some(soon) && l.revoked_at.is_some()));
+        assert_eq!(revoke(later).unwrap().links_sharing_key, 0);
+
+        close();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,590 @@
+use aws_sdk_s3::Client as S3Client;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::error::BucketStackError;
+use crate::object_index;
+
+// Global database connection (lazy initialized)
+static ANALYTICS_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+const PARALLEL_LISTINGS: usize = 8;
+// Delimited levels listed to find prefixes to crawl in parallel
+const MAX_SPLIT_DEPTH: usize = 2;
+const LARGEST_KEPT: usize = 50;
+// Older analyses keep their totals for trends but drop the full report
+const FULL_REPORTS_KEPT: i64 = 20;
+const AGE_BUCKETS: [(&str, i64); 5] = [
+    ("0-30 days", 30),
+    ("30-90 days", 90),
+    ("90-180 days", 180),
+    ("180-365 days", 365),
+    ("1+ year", i64::MAX),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    pub count: u64,
+    pub size: u64,
+}
+
+impl Tally {
+    fn add(&mut self, size: u64) {
+        self.count += 1;
+        self.size += size;
+    }
+}
+
+/// A folder with everything under it counted, largest children first
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FolderNode {
+    pub prefix: String,
+    pub count: u64,
+    pub size: u64,
+    pub children: Vec<FolderNode>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LargestObject {
+    pub size: u64,
+    pub key: String,
+    pub last_modified: Option<String>,
+    pub storage_class: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgeBucket {
+    pub label: String,
+    pub count: u64,
+    pub size: u64,
+}
+
+/// Multipart uploads that were started but never completed or aborted;
+/// their parts are billed like objects
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct IncompleteUploads {
+    pub uploads: u64,
+    pub parts: u64,
+    pub size: u64,
+    pub oldest_initiated: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BucketAnalysis {
+    pub id: Option<i64>,
+    pub endpoint: String,
+    pub bucket: String,
+    pub analyzed_at: String,
+    pub duration_ms: u64,
+    pub total: Tally,
+    pub folders: FolderNode,
+    pub storage_classes: BTreeMap<String, Tally>,
+    pub extensions: BTreeMap<String, Tally>,
+    pub age: Vec<AgeBucket>,
+    pub largest: Vec<LargestObject>,
+    // None when the provider wouldn't list multipart uploads
+    pub incomplete_uploads: Option<IncompleteUploads>,
+}
+
+/// Totals of one stored analysis, for charting a bucket over time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendPoint {
+    pub id: i64,
+    pub analyzed_at: String,
+    pub object_count: u64,
+    pub total_size: u64,
+    pub incomplete_upload_size: Option<u64>,
+    // False once the full report has been pruned
+    pub has_report: bool,
+}
+
+struct Accumulator {
+    now: DateTime<Utc>,
+    total: Tally,
+    folders: HashMap<String, Tally>,
+    storage_classes: HashMap<String, Tally>,
+    extensions: HashMap<String, Tally>,
+    age: [Tally; AGE_BUCKETS.len()],
+    largest: BinaryHeap<Reverse<LargestObject>>,
+}
+
+impl Accumulator {
+    fn new(now: DateTime<Utc>) -> Self {
+        Accumulator {
+            now,
+            total: Tally::default(),
+            folders: HashMap::new(),
+            storage_classes: HashMap::new(),
+            extensions: HashMap::new(),
+            age: Default::default(),
+            largest: BinaryHeap::new(),
+        }
+    }
+
+    fn add(&mut self, key: &str, size: u64, last_modified: Option<DateTime<Utc>>, storage_class: Option<&str>) {
+        // Folder markers only make their folders show up
+        if key.ends_with('/') {
+            for (i, _) in key.match_indices('/') {
+                self.folders.entry(key[..=i].to_string()).or_default();
+            }
+            return;
+        }
+
+        self.total.add(size);
+        for (i, _) in key.match_indices('/') {
+            self.folders.entry(key[..=i].to_string()).or_default().add(size);
+        }
+        let storage_class = storage_class.unwrap_or("STANDARD").to_string();
+        self.storage_classes.entry(storage_class.clone()).or_default().add(size);
+        let extension = object_index::extension(key).unwrap_or_else(|| "(none)".to_string());
+        self.extensions.entry(extension).or_default().add(size);
+        if let Some(modified) = last_modified {
+            let days = (self.now - modified).num_days();
+            if let Some(i) = AGE_BUCKETS.iter().position(|(_, max_days)| days < *max_days) {
+                self.age[i].add(size);
+            }
+        }
+
+        self.largest.push(Reverse(LargestObject {
+            size,
+            key: key.to_string(),
+            last_modified: last_modified.map(|t| t.to_rfc3339()),
+            storage_class,
+        }));
+        if self.largest.len() > LARGEST_KEPT {
+            self.largest.pop();
+        }
+    }
+
+    fn folder_tree(&self) -> FolderNode {
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for prefix in self.folders.keys() {
+            let parent = match prefix[..prefix.len() - 1].rfind('/') {
+                Some(i) => &prefix[..=i],
+                None => "",
+            };
+            children.entry(parent).or_default().push(prefix);
+        }
+
+        fn build(prefix: &str, tally: Tally, children: &HashMap<&str, Vec<&str>>, folders: &HashMap<String, Tally>) -> FolderNode {
+            let mut nodes: Vec<FolderNode> = children
+                .get(prefix)
+                .map(|list| list.iter().map(|child| build(child, folders[*child], children, folders)).collect())
+                .unwrap_or_default();
+            nodes.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.prefix.cmp(&b.prefix)));
+            FolderNode {
+                prefix: prefix.to_string(),
+                count: tally.count,
+                size: tally.size,
+                children: nodes,
+            }
+        }
+
+        build("", self.total, &children, &self.folders)
+    }
+
+    fn finish(self, endpoint: &str, bucket: &str, started: Instant, incomplete_uploads: Option<IncompleteUploads>) -> BucketAnalysis {
+        BucketAnalysis {
+            id: None,
+            endpoint: endpoint.to_string(),
+            bucket: bucket.to_string(),
+            analyzed_at: self.now.to_rfc3339(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            total: self.total,
+            folders: self.folder_tree(),
+            storage_classes: self.storage_classes.into_iter().collect(),
+            extensions: self.extensions.into_iter().collect(),
+            age: AGE_BUCKETS
+                .iter()
+                .zip(self.age)
+                .map(|((label, _), tally)| AgeBucket { label: label.to_string(), count: tally.count, size: tally.size })
+                .collect(),
+            largest: self.largest.into_sorted_vec().into_iter().map(|Reverse(o)| o).collect(),
+            incomplete_uploads,
+        }
+    }
+}
+
+fn to_utc(t: &aws_smithy_types::DateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(t.secs(), t.subsec_nanos())
+}
+
+// Every page under `prefix` into the accumulator; returns the common
+// prefixes when `delimited`
+async fn list_into(
+    client: &S3Client,
+    bucket: &str,
+    prefix: &str,
+    delimited: bool,
+    acc: &Mutex<Accumulator>,
+    progress: &(impl Fn(u64, u64) + Sync),
+) -> Result<Vec<String>, BucketStackError> {
+    let mut prefixes = Vec::new();
+    let mut continuation_token: Option<String> = None;
+    loop {
+        let mut req = client
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .set_continuation_token(continuation_token.take());
+        if delimited {
+            req = req.delimiter("/");
+        }
+        let page = req.send().await.map_err(|e| BucketStackError::wrap("Failed to list objects for analysis", e))?;
+
+        prefixes.extend(page.common_prefixes().iter().filter_map(|p| p.prefix()).map(str::to_string));
+        let total = {
+            let mut acc = acc.lock().unwrap();
+            for obj in page.contents() {
+                if let Some(key) = obj.key() {
+                    acc.add(
+                        key,
+                        obj.size().unwrap_or(0).max(0) as u64,
+                        obj.last_modified().and_then(to_utc),
+                        obj.storage_class().map(|s| s.as_str()),
+                    );
+                }
+            }
+            acc.total
+        };
+        progress(total.count, total.size);
+
+        match page.next_continuation_token() {
+            Some(token) if page.is_truncated().unwrap_or(false) => continuation_token = Some(token.to_string()),
+            _ => break,
+        }
+    }
+    Ok(prefixes)
+}
+
+async fn upload_parts(client: &S3Client, bucket: &str, key: &str, upload_id: &str) -> Result<(u64, u64), BucketStackError> {
+    let (mut parts, mut size) = (0u64, 0u64);
+    let mut marker: Option<String> = None;
+    loop {
+        let page = client
+            .list_parts()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .set_part_number_marker(marker.take())
+            .send()
+            .await
+            .map_err(|e| BucketStackError::wrap("Failed to list upload parts", e))?;
+        for part in page.parts() {
+            parts += 1;
+            size += part.size().unwrap_or(0).max(0) as u64;
+        }
+        match page.next_part_number_marker() {
+            Some(next) if page.is_truncated().unwrap_or(false) => marker = Some(next.to_string()),
+            _ => break,
+        }
+    }
+    Ok((parts, size))
+}
+
+async fn incomplete_uploads(client: &S3Client, bucket: &str) -> Result<IncompleteUploads, BucketStackError> {
+    let mut uploads = Vec::new();
+    let (mut key_marker, mut upload_id_marker): (Option<String>, Option<String>) = (None, None);
+    loop {
+        let page = client
+            .list_multipart_uploads()
+            .bucket(bucket)
+            .set_key_marker(key_marker.take())
+            .set_upload_id_marker(upload_id_marker.take())
+            .send()
+            .await
+            .map_err(|e| BucketStackError::wrap("Failed to list multipart uploads", e))?;
+        for upload in page.uploads() {
+            if let (Some(key), Some(id)) = (upload.key(), upload.upload_id()) {
+                uploads.push((key.to_string(), id.to_string(), upload.initiated().and_then(to_utc)));
+            }
+        }
+        if !page.is_truncated().unwrap_or(false) {
+            break;
+        }
+        key_marker = page.next_key_marker().map(str::to_string);
+        upload_id_marker = page.next_upload_id_marker().map(str::to_string);
+        if key_marker.is_none() {
+            break;
+        }
+    }
+
+    let mut summary = IncompleteUploads {
+        uploads: uploads.len() as u64,
+        oldest_initiated: uploads.iter().filter_map(|(_, _, initiated)| *initiated).min().map(|t| t.to_rfc3339()),
+        ..Default::default()
+    };
+    let sizes: Vec<(u64, u64)> = stream::iter(uploads.iter())
+        .map(|(key, id, _)| upload_parts(client, bucket, key, id))
+        .buffer_unordered(PARALLEL_LISTINGS)
+        .try_collect()
+        .await?;
+    for (parts, size) in sizes {
+        summary.parts += parts;
+        summary.size += size;
+    }
+    Ok(summary)
+}
+
+/// Crawl the whole bucket once. The top levels are listed by folder to find
+/// prefixes, which are then listed recursively in parallel. `progress` gets
+/// the running object count and bytes.
+pub async fn analyze(
+    client: &S3Client,
+    endpoint: &str,
+    bucket: &str,
+    progress: impl Fn(u64, u64) + Sync,
+) -> Result<BucketAnalysis, BucketStackError> {
+    let started = Instant::now();
+    let acc = Mutex::new(Accumulator::new(Utc::now()));
+
+    let mut frontier = vec![String::new()];
+    for _ in 0..MAX_SPLIT_DEPTH {
+        let mut next = Vec::new();
+        for prefix in &frontier {
+            next.extend(list_into(client, bucket, prefix, true, &acc, &progress).await?);
+        }
+        frontier = next;
+        if frontier.len() >= PARALLEL_LISTINGS {
+            break;
+        }
+    }
+
+    stream::iter(frontier.iter())
+        .map(|prefix| list_into(client, bucket, prefix, false, &acc, &progress))
+        .buffer_unordered(PARALLEL_LISTINGS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let incomplete = match incomplete_uploads(client, bucket).await {
+        Ok(summary) => Some(summary),
+        Err(e) => {
+            eprintln!("Skipping incomplete uploads for {}: {}", bucket, e.message);
+            None
+        }
+    };
+
+    Ok(acc.into_inner().unwrap().finish(endpoint, bucket, started, incomplete))
+}
+
+fn endpoint_key(endpoint: &str) -> String {
+    endpoint.trim().trim_end_matches('/').to_string()
+}
+
+pub fn init_analytics_db(app_data_dir: &Path) -> Result<(), String> {
+    let db_path = app_data_dir.join("analytics.db");
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open analytics database: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS bucket_analyses (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            endpoint TEXT NOT NULL,
+            bucket TEXT NOT NULL,
+            analyzed_at TEXT NOT NULL,
+            object_count INTEGER NOT NULL,
+            total_size INTEGER NOT NULL,
+            incomplete_upload_size INTEGER,
+            report TEXT
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create bucket_analyses table: {}", e))?;
+
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_bucket_analyses_bucket ON bucket_analyses(endpoint, bucket, analyzed_at)",
+        [],
+    );
+
+    let mut db = ANALYTICS_DB.lock().unwrap();
+    *db = Some(conn);
+
+    Ok(())
+}
+
+pub fn close() {
+    let mut db = ANALYTICS_DB.lock().unwrap();
+    *db = None;
+}
+
+fn with_db<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, BucketStackError> {
+    let db_guard = ANALYTICS_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Analytics database not initialized")?;
+    f(conn).map_err(|e| BucketStackError::wrap("Analytics history", e))
+}
+
+/// Store an analysis and set its id
+pub fn save(analysis: &mut BucketAnalysis) -> Result<(), BucketStackError> {
+    let report = serde_json::to_string(analysis).map_err(|e| BucketStackError::wrap("Failed to serialize analysis", e))?;
+    let endpoint = endpoint_key(&analysis.endpoint);
+    let id = with_db(|conn| {
+        conn.execute(
+            "INSERT INTO bucket_analyses (endpoint, bucket, analyzed_at, object_count, total_size, incomplete_upload_size, report)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                endpoint,
+                analysis.bucket,
+                analysis.analyzed_at,
+                analysis.total.count as i64,
+                analysis.total.size as i64,
+                analysis.incomplete_uploads.as_ref().map(|u| u.size as i64),
+                report,
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        conn.execute(
+            "UPDATE bucket_analyses SET report = NULL
+             WHERE endpoint = ?1 AND bucket = ?2 AND report IS NOT NULL AND id NOT IN (
+                SELECT id FROM bucket_analyses WHERE endpoint = ?1 AND bucket = ?2 ORDER BY id DESC LIMIT ?3
+             )",
+            params![endpoint, analysis.bucket, FULL_REPORTS_KEPT],
+        )?;
+        Ok(id)
+    })?;
+    analysis.id = Some(id);
+    Ok(())
+}
+
+/// A stored analysis, the latest one when `id` is None
+pub fn load(endpoint: &str, bucket: &str, id: Option<i64>) -> Result<Option<BucketAnalysis>, BucketStackError> {
+    let row: Option<(i64, String)> = with_db(|conn| {
+        conn.query_row(
+            "SELECT id, report FROM bucket_analyses
+             WHERE endpoint = ?1 AND bucket = ?2 AND report IS NOT NULL AND (?3 IS NULL OR id = ?3)
+             ORDER BY id DESC LIMIT 1",
+            params![endpoint_key(endpoint), bucket, id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+    })?;
+    let Some((id, report)) = row else {
+        return Ok(None);
+    };
+    let mut analysis: BucketAnalysis =
+        serde_json::from_str(&report).map_err(|e| BucketStackError::wrap("Stored analysis is unreadable", e))?;
+    analysis.id = Some(id);
+    Ok(Some(analysis))
+}
+
+pub fn history(endpoint: &str, bucket: &str) -> Result<Vec<TrendPoint>, BucketStackError> {
+    with_db(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, analyzed_at, object_count, total_size, incomplete_upload_size, report IS NOT NULL
+             FROM bucket_analyses WHERE endpoint = ?1 AND bucket = ?2 ORDER BY id",
+        )?;
+        let points = stmt
+            .query_map(params![endpoint_key(endpoint), bucket], |row| {
+                Ok(TrendPoint {
+                    id: row.get(0)?,
+                    analyzed_at: row.get(1)?,
+                    object_count: row.get::<_, i64>(2)? as u64,
+                    total_size: row.get::<_, i64>(3)? as u64,
+                    incomplete_upload_size: row.get::<_, Option<i64>>(4)?.map(|s| s as u64),
+                    has_report: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>();
+        points
+    })
+}
+
+pub fn forget_bucket(endpoint: &str, bucket: &str) -> Result<(), BucketStackError> {
+    with_db(|conn| {
+        conn.execute(
+            "DELETE FROM bucket_analyses WHERE endpoint = ?1 AND bucket = ?2",
+            params![endpoint_key(endpoint), bucket],
+        )
+    })
+    .map(|_| ())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn folder_rows(node: &FolderNode, csv: &mut String) {
+    if !node.prefix.is_empty() {
+        csv.push_str(&format!("folder,{},{},{}\n", csv_field(&node.prefix), node.count, node.size));
+    }
+    for child in &node.children {
+        folder_rows(child, csv);
+    }
+}
+
+/// `format` is "json" (the full report) or "csv" (one row per figure:
+/// section, name, objects, bytes)
+pub fn export(analysis: &BucketAnalysis, format: &str) -> Result<String, BucketStackError> {
+    match format {
+        "json" => serde_json::to_string_pretty(analysis)
+            .map_err(|e| BucketStackError::wrap("Failed to serialize to JSON", e)),
+        "csv" => {
+            let mut csv = String::from("Section,Name,Objects,Bytes\n");
+            csv.push_str(&format!("total,{},{},{}\n", csv_field(&analysis.bucket), analysis.total.count, analysis.total.size));
+            for (class, tally) in &analysis.storage_classes {
+                csv.push_str(&format!("storage_class,{},{},{}\n", csv_field(class), tally.count, tally.size));
+            }
+            for (extension, tally) in &analysis.extensions {
+                csv.push_str(&format!("extension,{},{},{}\n", csv_field(extension), tally.count, tally.size));
+            }
+            for bucket in &analysis.age {
+                csv.push_str(&format!("age,{},{},{}\n", csv_field(&bucket.label), bucket.count, bucket.size));
+            }
+            for object in &analysis.largest {
+                csv.push_str(&format!("largest,{},1,{}\n", csv_field(&object.key), object.size));
+            }
+            if let Some(uploads) = &analysis.incomplete_uploads {
+                csv.push_str(&format!("incomplete_uploads,,{},{}\n", uploads.uploads, uploads.size));
+            }
+            folder_rows(&analysis.folders, &mut csv);
+            Ok(csv)
+        }
+        _ => Err(BucketStackError::invalid("Unsupported format")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accumulator_builds_folder_tree_and_histograms() {
+        let now = Utc::now();
+        let mut acc = Accumulator::new(now);
+        acc.add("readme.md", 10, Some(now), None);
+        acc.add("logs/", 0, None, None);
+        acc.add("photos/2024/a.jpg", 300, Some(now - chrono::Duration::days(400)), Some("GLACIER"));
+        acc.add("photos/2024/b.JPG", 200, Some(now - chrono::Duration::days(45)), None);
+        acc.add("photos/c.png", 50, Some(now), None);
+
+        let analysis = acc.finish("", "b", Instant::now(), None);
+        assert_eq!(analysis.total, Tally { count: 4, size: 560 });
+
+        let root = &analysis.folders;
+        assert_eq!(root.children.iter().map(|c| c.prefix.as_str()).collect::<Vec<_>>(), ["photos/", "logs/"]);
+        let photos = &root.children[0];
+        assert_eq!((photos.count, photos.size), (3, 550));
+        assert_eq!(photos.children[0].prefix, "photos/2024/");
+        assert_eq!(photos.children[0].size, 500);
+
+        assert_eq!(analysis.extensions["jpg"], Tally { count: 2, size: 500 });
+        assert_eq!(analysis.storage_classes["GLACIER"].size, 300);
+        assert_eq!(analysis.age[0].count, 2);
+        assert_eq!(analysis.age[1].size, 200);
+        assert_eq!(analysis.age[4].size, 300);
+        assert_eq!(analysis.largest[0].key, "photos/2024/a.jpg");
+
+        let csv = export(&analysis, "csv").unwrap();
+        assert!(csv.contains("folder,photos/2024/,2,500\n"));
+    }
+}
@@ -0,0 +1,618 @@
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::context::{
+    BeforeTransmitInterceptorContextRef, FinalizerInterceptorContextRef,
+};
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::config_bag::{ConfigBag, Storable, StoreReplace};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use directories::ProjectDirs;
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::Instant;
+
+const PRICES_FILE: &str = "usage_prices.json";
+// Samples are written in batches; a LIST-heavy search issues thousands
+const FLUSH_THRESHOLD: usize = 200;
+const RETENTION_DAYS: i64 = 90;
+
+static USAGE_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+static PENDING: Lazy<Mutex<Vec<RequestSample>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static PRICES: Lazy<RwLock<Vec<PriceTable>>> = Lazy::new(|| RwLock::new(load_prices()));
+
+/// One HTTP request (retries count separately, as providers bill them)
+#[derive(Debug, Clone)]
+struct RequestSample {
+    timestamp: String,
+    connection_id: String,
+    provider: String,
+    operation: String,
+    http_status: Option<u16>,
+    is_error: bool,
+    bytes_sent: u64,
+    bytes_received: u64,
+    latency_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestClass {
+    // Writes, copies and listings (AWS PUT/COPY/POST/LIST, R2 Class A)
+    ClassA,
+    // Reads and everything else (AWS GET, R2 Class B)
+    ClassB,
+    // Deletes and aborted uploads
+    Free,
+}
+
+/// What a provider charges, in `currency`. `overrides` moves operations
+/// (by SDK name, e.g. "PutObject") to another class.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PriceTable {
+    pub provider: String,
+    pub currency: String,
+    pub class_a_per_million: f64,
+    pub class_b_per_million: f64,
+    // Data downloaded from the provider
+    pub egress_per_gb: f64,
+    #[serde(default)]
+    pub overrides: HashMap<String, RequestClass>,
+}
+
+fn price(provider: &str, class_a: f64, class_b: f64, egress: f64, free: &[&str]) -> PriceTable {
+    PriceTable {
+        provider: provider.to_string(),
+        currency: "USD".to_string(),
+        class_a_per_million: class_a,
+        class_b_per_million: class_b,
+        egress_per_gb: egress,
+        overrides: free.iter().map(|op| (op.to_string(), RequestClass::Free)).collect(),
+    }
+}
+
+// List prices at the time of writing; users can edit them
+pub fn default_prices() -> Vec<PriceTable> {
+    vec![
+        price("aws", 5.0, 0.4, 0.09, &[]),
+        price("cloudflare", 4.5, 0.36, 0.0, &[]),
+        // B2 uploads are free; its Class C (listings) is priced as Class A here
+        price("backblaze", 4.0, 0.4, 0.01, &["PutObject", "UploadPart", "CreateMultipartUpload", "CompleteMultipartUpload"]),
+        price("wasabi", 0.0, 0.0, 0.0, &[]),
+        price("digitalocean", 0.0, 0.0, 0.01, &[]),
+        price("custom", 0.0, 0.0, 0.0, &[]),
+    ]
+}
+
+fn get_prices_path() -> PathBuf {
+    match ProjectDirs::from("com", "bucketstack", "app") {
+        Some(proj_dirs) => proj_dirs.config_dir().join(PRICES_FILE),
+        None => PathBuf::from(PRICES_FILE),
+    }
+}
+
+fn load_prices() -> Vec<PriceTable> {
+    match fs::read_to_string(get_prices_path()) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Warning: Invalid price tables file, using defaults: {}", e);
+            default_prices()
+        }),
+        Err(_) => default_prices(),
+    }
+}
+
+pub fn price_tables() -> Vec<PriceTable> {
+    PRICES.read().unwrap().clone()
+}
+
+pub fn save_price_tables(tables: Vec<PriceTable>) -> Result<(), String> {
+    if let Some(bad) = tables.iter().find(|t| {
+        [t.class_a_per_million, t.class_b_per_million, t.egress_per_gb]
+            .iter()
+            .any(|p| !p.is_finite() || *p < 0.0)
+    }) {
+        return Err(format!("Prices for {} must be zero or more", bad.provider));
+    }
+
+    let path = get_prices_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&tables).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write price tables: {}", e))?;
+
+    *PRICES.write().unwrap() = tables;
+    Ok(())
+}
+
+fn classify(operation: &str, table: Option<&PriceTable>) -> RequestClass {
+    if let Some(class) = table.and_then(|t| t.overrides.get(operation)) {
+        return *class;
+    }
+    if operation.starts_with("Delete") || operation == "AbortMultipartUpload" {
+        RequestClass::Free
+    } else if ["Put", "List", "Create", "Copy", "UploadPart", "Complete", "Restore", "Post"]
+        .iter()
+        .any(|prefix| operation.starts_with(prefix))
+    {
+        RequestClass::ClassA
+    } else {
+        RequestClass::ClassB
+    }
+}
+
+fn timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn init_usage_db(app_data_dir: &Path) -> Result<(), String> {
+    let db_path = app_data_dir.join("usage.db");
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open usage database: {}", e))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS request_metrics (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp TEXT NOT NULL,
+            connection_id TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            operation TEXT NOT NULL,
+            http_status INTEGER,
+            is_error INTEGER NOT NULL,
+            bytes_sent INTEGER NOT NULL,
+            bytes_received INTEGER NOT NULL,
+            latency_ms INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| format!("Failed to create request_metrics table: {}", e))?;
+
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_metrics_timestamp ON request_metrics(timestamp)",
+        [],
+    );
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_metrics_connection ON request_metrics(connection_id)",
+        [],
+    );
+    let _ = conn.execute(
+        "DELETE FROM request_metrics WHERE timestamp < ?1",
+        params![timestamp(Utc::now() - Duration::days(RETENTION_DAYS))],
+    );
+
+    let mut db = USAGE_DB.lock().unwrap();
+    *db = Some(conn);
+
+    Ok(())
+}
+
+pub fn close() {
+    flush();
+    let mut db = USAGE_DB.lock().unwrap();
+    *db = None;
+}
+
+fn record(sample: RequestSample) {
+    let full = {
+        let mut pending = PENDING.lock().unwrap();
+        pending.push(sample);
+        pending.len() >= FLUSH_THRESHOLD
+    };
+    if full {
+        flush();
+    }
+}
+
+/// Write buffered samples (best effort; metrics never fail a request)
+pub fn flush() {
+    let samples = std::mem::take(&mut *PENDING.lock().unwrap());
+    if samples.is_empty() {
+        return;
+    }
+    let mut db_guard = USAGE_DB.lock().unwrap();
+    let Some(conn) = db_guard.as_mut() else {
+        return;
+    };
+
+    let result = conn.transaction().and_then(|tx| {
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO request_metrics (
+                    timestamp, connection_id, provider, operation, http_status,
+                    is_error, bytes_sent, bytes_received, latency_ms
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for s in &samples {
+                stmt.execute(params![
+                    s.timestamp,
+                    s.connection_id,
+                    s.provider,
+                    s.operation,
+                    s.http_status,
+                    s.is_error,
+                    s.bytes_sent as i64,
+                    s.bytes_received as i64,
+                    s.latency_ms as i64,
+                ])?;
+            }
+        }
+        tx.commit()
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to record request metrics: {}", e);
+    }
+}
+
+// Streaming bodies haven't been read yet, so Content-Length is what will be.
+// HEAD, 204 and 304 responses carry the length of a body that isn't sent.
+fn response_bytes(method: &str, http_status: Option<u16>, content_length: Option<&str>) -> u64 {
+    if method.eq_ignore_ascii_case("HEAD") || matches!(http_status, Some(204 | 304)) {
+        return 0;
+    }
+    content_length.and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+#[derive(Debug)]
+struct AttemptStart {
+    started: Instant,
+    bytes_sent: u64,
+    method: String,
+}
+
+impl Storable for AttemptStart {
+    type Storer = StoreReplace<Self>;
+}
+
+/// Counts every request an S3 client sends
+#[derive(Debug)]
+pub struct UsageInterceptor {
+    connection_id: String,
+    provider: String,
+}
+
+impl UsageInterceptor {
+    pub fn new(connection_id: String, provider: String) -> Self {
+        Self { connection_id, provider }
+    }
+}
+
+impl Intercept for UsageInterceptor {
+    fn name(&self) -> &'static str {
+        "UsageInterceptor"
+    }
+
+    fn read_before_attempt(
+        &self,
+        context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let request = context.request();
+        let bytes_sent = request.body().content_length().unwrap_or(0);
+        let method = request.method().to_string();
+        cfg.interceptor_state().store_put(AttemptStart { started: Instant::now(), bytes_sent, method });
+        Ok(())
+    }
+
+    fn read_after_attempt(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        // Attempts that failed before reaching the wire aren't billed
+        let Some(start) = cfg.load::<AttemptStart>() else {
+            return Ok(());
+        };
+        let response = context.response();
+        let http_status = response.map(|r| r.status().as_u16());
+        let bytes_received = response_bytes(
+            &start.method,
+            http_status,
+            response.and_then(|r| r.headers().get("content-length")),
+        );
+        let is_error = http_status.is_none_or(|s| s >= 400)
+            || context.output_or_error().is_some_and(|r| r.is_err());
+
+        record(RequestSample {
+            timestamp: timestamp(Utc::now()),
+            connection_id: self.connection_id.clone(),
+            provider: self.provider.clone(),
+            operation: cfg.load::<Metadata>().map_or("Unknown", |m| m.name()).to_string(),
+            http_status,
+            is_error,
+            bytes_sent: start.bytes_sent,
+            bytes_received,
+            latency_ms: start.started.elapsed().as_millis() as u64,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OperationUsage {
+    pub operation: String,
+    pub class: RequestClass,
+    pub requests: u64,
+    pub errors: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub estimated_cost: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConnectionUsage {
+    pub connection_id: String,
+    pub provider: String,
+    pub currency: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub estimated_cost: f64,
+    // Most requested first
+    pub operations: Vec<OperationUsage>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UsageReport {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub connections: Vec<ConnectionUsage>,
+}
+
+// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Default)]
+struct Totals {
+    requests: u64,
+    errors: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    latencies: Vec<u64>,
+}
+
+impl Totals {
+    fn add(&mut self, sample: &RequestSample) {
+        self.requests += 1;
+        self.errors += sample.is_error as u64;
+        self.bytes_sent += sample.bytes_sent;
+        self.bytes_received += sample.bytes_received;
+        self.latencies.push(sample.latency_ms);
+    }
+
+    fn percentiles(&mut self) -> (u64, u64, u64) {
+        self.latencies.sort_unstable();
+        (
+            percentile(&self.latencies, 50.0),
+            percentile(&self.latencies, 90.0),
+            percentile(&self.latencies, 99.0),
+        )
+    }
+}
+
+fn cost(class: RequestClass, requests: u64, bytes_received: u64, table: Option<&PriceTable>) -> f64 {
+    let Some(table) = table else {
+        return 0.0;
+    };
+    let per_million = match class {
+        RequestClass::ClassA => table.class_a_per_million,
+        RequestClass::ClassB => table.class_b_per_million,
+        RequestClass::Free => 0.0,
+    };
+    requests as f64 / 1_000_000.0 * per_million + bytes_received as f64 / 1e9 * table.egress_per_gb
+}
+
+fn build_report(samples: &[RequestSample], tables: &[PriceTable]) -> Vec<ConnectionUsage> {
+    let mut grouped: BTreeMap<(&str, &str), BTreeMap<&str, Totals>> = BTreeMap::new();
+    for sample in samples {
+        grouped
+            .entry((&sample.connection_id, &sample.provider))
+            .or_default()
+            .entry(&sample.operation)
+            .or_default()
+            .add(sample);
+    }
+
+    grouped
+        .into_iter()
+        .map(|((connection_id, provider), operations)| {
+            let table = tables
+                .iter()
+                .find(|t| t.provider == provider)
+                .or_else(|| tables.iter().find(|t| t.provider == "custom"));
+            let mut all = Totals::default();
+            let mut operations: Vec<OperationUsage> = operations
+                .into_iter()
+                .map(|(operation, mut totals)| {
+                    let class = classify(operation, table);
+                    let (p50_ms, p90_ms, p99_ms) = totals.percentiles();
+                    all.requests += totals.requests;
+                    all.errors += totals.errors;
+                    all.bytes_sent += totals.bytes_sent;
+                    all.bytes_received += totals.bytes_received;
+                    all.latencies.extend_from_slice(&totals.latencies);
+                    OperationUsage {
+                        operation: operation.to_string(),
+                        class,
+                        requests: totals.requests,
+                        errors: totals.errors,
+                        bytes_sent: totals.bytes_sent,
+                        bytes_received: totals.bytes_received,
+                        p50_ms,
+                        p90_ms,
+                        p99_ms,
+                        estimated_cost: cost(class, totals.requests, totals.bytes_received, table),
+                    }
+                })
+                .collect();
+            operations.sort_by_key(|o| std::cmp::Reverse(o.requests));
+
+            let (p50_ms, p90_ms, p99_ms) = all.percentiles();
+            ConnectionUsage {
+                connection_id: connection_id.to_string(),
+                provider: provider.to_string(),
+                currency: table.map_or("USD", |t| t.currency.as_str()).to_string(),
+                requests: all.requests,
+                errors: all.errors,
+                bytes_sent: all.bytes_sent,
+                bytes_received: all.bytes_received,
+                p50_ms,
+                p90_ms,
+                p99_ms,
+                estimated_cost: operations.iter().map(|o| o.estimated_cost).sum(),
+                operations,
+            }
+        })
+        .collect()
+}
+
+/// Requests and estimated cost per connection between `since` and `until`
+/// (RFC 3339, both optional)
+pub fn report(connection_id: Option<String>, since: Option<String>, until: Option<String>) -> Result<UsageReport, String> {
+    flush();
+    let normalize = |value: &Option<String>| -> Result<Option<String>, String> {
+        value
+            .as_deref()
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                DateTime::parse_from_rfc3339(v)
+                    .map(|t| timestamp(t.with_timezone(&Utc)))
+                    .map_err(|e| format!("Invalid date '{}': {}", v, e))
+            })
+            .transpose()
+    };
+    let (since, until) = (normalize(&since)?, normalize(&until)?);
+
+    let mut sql = String::from(
+        "SELECT timestamp, connection_id, provider, operation, http_status, is_error, bytes_sent, bytes_received, latency_ms FROM request_metrics WHERE 1=1",
+    );
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    if let Some(conn_id) = connection_id {
+        sql.push_str(" AND connection_id = ?");
+        params_vec.push(Box::new(conn_id));
+    }
+    if let Some(since) = &since {
+        sql.push_str(" AND timestamp >= ?");
+        params_vec.push(Box::new(since.clone()));
+    }
+    if let Some(until) = &until {
+        sql.push_str(" AND timestamp < ?");
+        params_vec.push(Box::new(until.clone()));
+    }
+
+    let samples = {
+        let db_guard = USAGE_DB.lock().unwrap();
+        let conn = db_guard.as_ref().ok_or("Usage database not initialized")?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|b| b.as_ref()).collect();
+        let mut stmt = conn.prepare(&sql)
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let samples = stmt.query_map(params_refs.as_slice(), |row| {
+            Ok(RequestSample {
+                timestamp: row.get(0)?,
+                connection_id: row.get(1)?,
+                provider: row.get(2)?,
+                operation: row.get(3)?,
+                http_status: row.get(4)?,
+                is_error: row.get(5)?,
+                bytes_sent: row.get::<_, i64>(6)? as u64,
+                bytes_received: row.get::<_, i64>(7)? as u64,
+                latency_ms: row.get::<_, i64>(8)? as u64,
+            })
+        })
+        .map_err(|e| format!("Failed to query: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect results: {}", e))?;
+        samples
+    };
+
+    Ok(UsageReport {
+        since,
+        until,
+        connections: build_report(&samples, &price_tables()),
+    })
+}
+
+pub fn clear(connection_id: Option<String>) -> Result<usize, String> {
+    flush();
+    let db_guard = USAGE_DB.lock().unwrap();
+    let conn = db_guard.as_ref().ok_or("Usage database not initialized")?;
+    match connection_id {
+        Some(id) => conn.execute("DELETE FROM request_metrics WHERE connection_id = ?1", params![id]),
+        None => conn.execute("DELETE FROM request_metrics", []),
+    }
+    .map_err(|e| format!("Failed to clear request metrics: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(operation: &str, latency_ms: u64, bytes_received: u64) -> RequestSample {
+        RequestSample {
+            timestamp: timestamp(Utc::now()),
+            connection_id: "conn".to_string(),
+            provider: "aws".to_string(),
+            operation: operation.to_string(),
+            http_status: Some(200),
+            is_error: false,
+            bytes_sent: 0,
+            bytes_received,
+            latency_ms,
+        }
+    }
+
+    #[test]
+    fn test_classification_and_percentiles() {
+        assert_eq!(classify("ListObjectsV2", None), RequestClass::ClassA);
+        assert_eq!(classify("GetObject", None), RequestClass::ClassB);
+        assert_eq!(classify("DeleteObjects", None), RequestClass::Free);
+        let b2 = default_prices().into_iter().find(|t| t.provider == "backblaze");
+        assert_eq!(classify("PutObject", b2.as_ref()), RequestClass::Free);
+
+        let latencies: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&latencies, 50.0), 50);
+        assert_eq!(percentile(&latencies, 99.0), 99);
+        assert_eq!(percentile(&[], 90.0), 0);
+    }
+
+    #[test]
+    fn test_bodyless_responses_receive_nothing() {
+        assert_eq!(response_bytes("GET", Some(200), Some("1048576")), 1_048_576);
+        assert_eq!(response_bytes("HEAD", Some(200), Some("1048576")), 0);
+        assert_eq!(response_bytes("GET", Some(304), Some("1048576")), 0);
+        assert_eq!(response_bytes("DELETE", Some(204), None), 0);
+    }
+
+    #[test]
+    fn test_report_estimates_cost() {
+        let mut samples: Vec<RequestSample> = (0..1000).map(|i| sample("ListObjectsV2", i, 0)).collect();
+        samples.push(sample("GetObject", 5, 2_000_000_000));
+
+        let report = build_report(&samples, &default_prices());
+        assert_eq!(report.len(), 1);
+        let usage = &report[0];
+        assert_eq!(usage.requests, 1001);
+        assert_eq!(usage.operations[0].operation, "ListObjectsV2");
+        // 1000 LISTs at $5/M plus 2 GB of egress at $0.09/GB and one GET
+        let expected = 0.005 + 0.18 + 0.4 / 1_000_000.0;
+        assert!((usage.estimated_cost - expected).abs() < 1e-9);
+    }
+}
@@ -11,6 +11,8 @@ export interface S3AccountMetadata {
   accessMode?: 'read-only' | 'read-write';
   enableTrash?: boolean;
   enableActivityLog?: boolean;
+  // Stored without its secret fields, which live in secure storage
+  connection?: ConnectionOptions;
 }
 
 // Full account with credentials (used internally, credentials stored securely)
@@ -19,6 +21,54 @@ export interface S3Account extends S3AccountMetadata {
   secretAccessKey: string;
 }
 
+// Per-connection settings sent with every S3 command. Mirrors
+// ConnectionOptions in src/connection.rs, so fields are snake_case.
+export interface ConnectionOptions {
+  connection_id?: string;
+  session_token?: string;
+  assume_role?: {
+    role_arn: string;
+    external_id?: string;
+    session_name?: string;
+    duration_seconds?: number;
+    mfa_serial?: string;
+    mfa_token?: string;
+    sts_endpoint?: string;
+  };
+  web_identity?: {
+    role_arn: string;
+    token?: string;
+    token_file?: string;
+    session_name?: string;
+    duration_seconds?: number;
+    sts_endpoint?: string;
+  };
+  profile?: string;
+  use_default_chain?: boolean;
+  network?: {
+    max_attempts?: number;
+    retry_mode?: 'standard' | 'adaptive';
+    connect_timeout_secs?: number;
+    read_timeout_secs?: number;
+    operation_timeout_secs?: number;
+    operation_attempt_timeout_secs?: number;
+    pool_idle_timeout_secs?: number;
+  };
+  proxy?: {
+    url: string;
+    username?: string;
+    password?: string;
+    no_proxy?: string;
+  };
+  tls?: {
+    ca_certificates_pem?: string;
+    client_certificate_pem?: string;
+    client_key_pem?: string;
+    insecure_skip_verify?: boolean;
+  };
+  addressing_style?: 'auto' | 'path' | 'virtual';
+}
+
 export interface S3Bucket {
   name: string;
   creationDate: string;
@@ -74,6 +124,16 @@ export interface SyncStats {
   errors: string[];
 }
 
+// Error returned by every backend command (invoke rejects with this)
+export interface BucketStackError {
+  kind: string;
+  code?: string;
+  http_status?: number;
+  request_id?: string;
+  retryable: boolean;
+  message: string;
+}
+
 export interface ActivityLogEntry {
   id?: number;
   timestamp: string;
@@ -84,7 +144,7 @@ export interface ActivityLogEntry {
   object_path_before?: string;
   object_path_after?: string;
   status: 'success' | 'failed';
-  error_message?: string;
+  error_message?: BucketStackError;
   file_size?: number;
   source: string;
 }
@@ -144,4 +204,15 @@ export interface BucketAnalytics {
   storageClassDistribution: { storageClass: string; count: number; size: number }[];
   ageDistribution: { ageRange: string; count: number; size: number }[];
   lastUpdated: number;
+  // Set when the analysis came from the backend's stored history
+  id?: number;
+  incompleteUploads?: { uploads: number; parts: number; size: number } | null;
+}
+
+export interface AnalyticsTrendPoint {
+  id: number;
+  analyzedAt: number;
+  totalObjects: number;
+  totalSize: number;
+  incompleteUploadSize: number | null;
 }
\ No newline at end of file